@@ -6,6 +6,7 @@ use syn::{Item, Type, PathArguments, GenericArgument, Attribute};
 use quote::{quote, ToTokens, quote_spanned};
 use proc_macro2::Ident;
 use syn::spanned::Spanned;
+use syn::ext::IdentExt;
 
 struct TypeData {
     initialize: TokenStream,
@@ -237,6 +238,7 @@ fn simple_type_dump_list(simple_type: &str) -> &str {
         "i128" => "dump_integer",
         "ValueType" => "dump_type",
         "f64" => "dump_float",
+        "Field" => "dump_field",
         "Value" => "dump_value",
         _ => panic!("Unknown type"),
     }
@@ -250,10 +252,10 @@ fn type_to_value(
     is_unnamed_target: bool,
     allowed_values: Option<Vec<Literal>>,
 ) -> SignatureResult<TypeData> {
-    let name_literal = proc_macro2::Literal::string(&name.to_string());
+    let name_literal = proc_macro2::Literal::string(&name.unraw().to_string());
 
     let allowed_values_name =
-        allowed_values.as_ref().map(|_| Ident::new(&format!("{}_allowed_values", name.to_string()), ty.span()));
+        allowed_values.as_ref().map(|_| Ident::new(&format!("{}_allowed_values", name.unraw().to_string()), ty.span()));
 
     let (type_name, args) = extract_type(ty)?;
     match type_name {
@@ -267,9 +269,9 @@ fn type_to_value(
                 Ok(TypeData {
                     signature:
                     if default.is_none() {
-                        format!("{}={}", name.to_string(), simple_type_to_value_description(type_name).to_string().to_lowercase())
+                        format!("{}={}", name.unraw().to_string(), simple_type_to_value_description(type_name).to_string().to_lowercase())
                     } else {
-                        format!("[{}={}]", name.to_string(), simple_type_to_value_description(type_name).to_string().to_lowercase())
+                        format!("[{}={}]", name.unraw().to_string(), simple_type_to_value_description(type_name).to_string().to_lowercase())
                     }
                     ,
                     initialize: match allowed_values {
@@ -333,7 +335,7 @@ if #name.is_none() {
                 fail!(ty.span(), "This type can't be paramterizised")
             } else {
                 Ok(TypeData {
-                    signature: format!("[{}=(file|glob|regex|list|table|table_stream)...]", name.to_string()),
+                    signature: format!("[{}=(file|glob|regex|list|table|table_stream)...]", name.unraw().to_string()),
                     initialize: quote! { let mut #name = crate::lang::files::Files::new(); },
                     mappings: quote! { (Some(#name_literal), value) => #name.expand(value, printer)?, },
                     unnamed_mutate: if is_unnamed_target {
@@ -360,7 +362,7 @@ if #name.is_none() {
                 let value_type = simple_type_to_value(args[0]);
 
                 Ok(TypeData {
-                    signature: format!("[{}={}...]", name.to_string(), simple_type_to_value_description(args[0]).to_string().to_lowercase()),
+                    signature: format!("[{}={}...]", name.unraw().to_string(), simple_type_to_value_description(args[0]).to_string().to_lowercase()),
                     initialize: quote! { let mut #name = Vec::new(); },
                     mappings: quote! {
                         (Some(#name_literal), #value_type) => #name.push(#mutator),
@@ -411,7 +413,7 @@ if #name.is_none() {
                 let value_type = simple_type_to_value(args[0]);
 
                 Ok(TypeData {
-                    signature: format!("[{}={}]", name.to_string(), simple_type_to_value_description(args[0]).to_string().to_lowercase()),
+                    signature: format!("[{}={}]", name.unraw().to_string(), simple_type_to_value_description(args[0]).to_string().to_lowercase()),
                     initialize: quote! { let mut #name = None; },
                     mappings: quote! { (Some(#name_literal), #value_type) => #name = Some(#mutator), },
                     unnamed_mutate: Some(quote_spanned! { ty.span() =>
@@ -586,6 +588,7 @@ fn signature_real(metadata: TokenStream, input: TokenStream) -> SignatureResult<
             let mut unnamed_mutations = proc_macro2::TokenStream::new();
             let mut assignments = proc_macro2::TokenStream::new();
             let mut named_fallback = proc_macro2::TokenStream::new();
+            let mut arguments = proc_macro2::TokenStream::new();
             let mut had_unnamed_target = false;
             let struct_name = s.ident.clone();
             let mut had_field_description = false;
@@ -612,6 +615,32 @@ fn signature_real(metadata: TokenStream, input: TokenStream) -> SignatureResult<
                 }
                 field.attrs = Vec::new();
                 let name = &field.ident.clone().unwrap();
+
+                if !is_named_target && extract_type(&field.ty)?.0 != "OrderedStringMap" {
+                    let name_literal = Literal::string(&name.unraw().to_string());
+                    let allowed = match &allowed_values {
+                        None => quote! {None},
+                        Some(literals) => {
+                            let literals = literals.iter()
+                                .map(|l| {
+                                    let s = l.to_string();
+                                    // Crush has no character literals, use strings instead
+                                    if s.starts_with('\'') {
+                                        Literal::string(&format!("\"{}\"", &s[1..s.len() - 1]))
+                                    } else {
+                                        Literal::string(&s)
+                                    }
+                                });
+                            quote! {Some(vec![#(#literals.to_string()),*])}
+                        }
+                    };
+                    arguments.extend(quote! {
+                        crate::lang::argument::ArgumentDescription {
+                            name: #name_literal.to_string(),
+                            allowed_values: #allowed,
+                        },
+                    });
+                }
                 let type_data = type_to_value(&field.ty, name, default_value.clone(), is_unnamed_target, allowed_values)?;
 
                 signature.push(type_data.signature);
@@ -636,7 +665,7 @@ fn signature_real(metadata: TokenStream, input: TokenStream) -> SignatureResult<
                         long_description.push("This command accepts the following arguments:".to_string());
                         had_field_description = true;
                     }
-                    long_description.push(format!("* {}{}, {}", name.to_string(), default_help, description));
+                    long_description.push(format!("* {}{}, {}", name.unraw().to_string(), default_help, description));
                 }
 
                 if !had_unnamed_target || default_value.is_some() {
@@ -671,12 +700,13 @@ fn signature_real(metadata: TokenStream, input: TokenStream) -> SignatureResult<
 
 impl crate::lang::argument::ArgumentHandler for #struct_name {
     fn declare(env: &mut crate::lang::scope::ScopeLoader) -> crate::lang::errors::CrushResult <()> {
-        env.declare_command(
+        env.declare_command_with_arguments(
             #command_name, #command_invocation, #can_block,
             #signature_literal,
             #description,
             #long_description,
-            #output)
+            #output,
+            vec![#arguments])
     }
 
     fn declare_method(env: &mut ordered_map::OrderedMap<std::string::String, crate::lang::command::Command>, path: &Vec<&str>) -> crate::lang::errors::CrushResult <()> {
@@ -685,7 +715,8 @@ impl crate::lang::argument::ArgumentHandler for #struct_name {
         env.insert(#command_name.to_string(),
                     crate::lang::command::CrushCommand::command(
                         #command_invocation, #can_block, full.iter().map(|e| e.to_string()).collect(),
-                        #signature_literal, #description, #long_description, #output));
+                        #signature_literal, #description, #long_description, #output,
+                        vec![#arguments]));
         Ok(())
     }
 
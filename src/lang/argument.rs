@@ -76,6 +76,17 @@ impl ArgumentDefinition {
     }
 }
 
+impl ToString for ArgumentDefinition {
+    fn to_string(&self) -> String {
+        match &self.argument_type {
+            ArgumentType::Some(name) => format!("{}={}", name, self.value.to_string()),
+            ArgumentType::None => self.value.to_string(),
+            ArgumentType::ArgumentList => format!("@{}", self.value.to_string()),
+            ArgumentType::ArgumentDict => format!("@@{}", self.value.to_string()),
+        }
+    }
+}
+
 pub type Argument = BaseArgument<Option<String>, Value>;
 
 impl Argument {
@@ -101,6 +112,16 @@ impl Argument {
     }
 }
 
+/**
+A named argument accepted by a command. Used for tab completion.
+*/
+#[derive(Clone, Debug)]
+pub struct ArgumentDescription {
+    pub name: String,
+    /// If only some values are allowed, the source code for each of them
+    pub allowed_values: Option<Vec<String>>,
+}
+
 pub trait ArgumentVecCompiler {
     fn compile(&self, context: &mut CompileContext) -> CrushResult<(Vec<Argument>, Option<Value>)>;
 }
@@ -1,5 +1,5 @@
 use crate::lang::job::Job;
-use crate::lang::errors::{CrushResult, error, to_crush_error};
+use crate::lang::errors::{CrushResult, error, to_crush_error, mandate};
 use crate::lang::command_invocation::CommandInvocation;
 use crate::lang::argument::ArgumentDefinition;
 use crate::lang::value::{ValueDefinition, Value, ValueType};
@@ -9,6 +9,13 @@ use crate::util::glob::Glob;
 use regex::Regex;
 use std::path::PathBuf;
 use crate::lang::scope::Scope;
+use crate::lang::source::Location;
+use crate::lang::parser::parse_at;
+use num_bigint::BigInt;
+use chrono::Duration;
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::ops::Range;
 
 pub struct JobListNode {
     pub jobs: Vec<JobNode>,
@@ -22,20 +29,30 @@ impl JobListNode {
 
 pub struct JobNode {
     pub commands: Vec<CommandNode>,
+    pub background: bool,
 }
 
 impl JobNode {
     pub fn generate(&self, env: &Scope) -> CrushResult<Job> {
-        Ok(Job::new(self.commands.iter().map(|c| c.generate(env)).collect::<CrushResult<Vec<CommandInvocation>>>()?))
+        Ok(Job::new(
+            self.commands.iter().map(|c| c.generate(env)).collect::<CrushResult<Vec<CommandInvocation>>>()?,
+            self.background))
     }
 }
 
 pub struct CommandNode {
     pub expressions: Vec<Node>,
+    pub location: Location,
 }
 
 impl CommandNode {
     pub fn generate(&self, env: &Scope) -> CrushResult<CommandInvocation> {
+        self.generate_internal(env)
+            .map(|c| c.with_location(self.location.clone()))
+            .map_err(|e| e.with_location(self.location.clone()))
+    }
+
+    fn generate_internal(&self, env: &Scope) -> CrushResult<CommandInvocation> {
         if let Some(c) = self.expressions[0].generate_standalone(env)? {
             if self.expressions.len() == 1 {
                 Ok(c)
@@ -64,10 +81,14 @@ pub enum Node {
     Label(String),
     Regex(String),
     Field(String),
-    String(String),
+    /** A string literal, including the quotes, and where it is in the source */
+    String(String, Location),
+    RawString(String),
     File(PathBuf),
-    Integer(i128),
+    Integer(BigInt),
     Float(f64),
+    Duration(String),
+    Size(String),
     GetItem(Box<Node>, Box<Node>),
     GetAttr(Box<Node>, String),
     Path(Box<Node>, String),
@@ -85,7 +106,7 @@ fn propose_name(name: &str, v: ValueDefinition) -> ValueDefinition {
                 CommandInvocation::new(
                     o,
                     vec![])
-            ]);
+            ], false);
             ValueDefinition::JobDefinition(j)
         }
     }
@@ -111,13 +132,13 @@ impl Node {
                 Node::LogicalOperation(_, _, _) | Node::Comparison(_, _, _) | Node::Replace(_, _, _, _) |
                 Node::GetItem(_, _) | Node::Term(_, _, _) | Node::Factor(_, _, _) =>
                     ValueDefinition::JobDefinition(
-                        Job::new(vec![self.generate_standalone(env)?.unwrap()])
+                        Job::new(vec![self.generate_standalone(env)?.unwrap()], false)
                     ),
                 Node::Unary(op, r) =>
                     match op.deref() {
                         "neg" | "not" | "typeof" =>
                             ValueDefinition::JobDefinition(
-                                Job::new(vec![self.generate_standalone(env)?.unwrap()])
+                                Job::new(vec![self.generate_standalone(env)?.unwrap()], false)
                             ),
                         "@" =>
                             return Ok(ArgumentDefinition::list(r.generate_argument(env)?.unnamed_value()?)),
@@ -127,9 +148,12 @@ impl Node {
                     },
                 Node::Label(l) => ValueDefinition::Label(l.clone()),
                 Node::Regex(l) => ValueDefinition::Value(Value::Regex(l.clone(), to_crush_error(Regex::new(l.clone().as_ref()))?)),
-                Node::String(t) => ValueDefinition::Value(Value::string(unescape(t).as_str())),
-                Node::Integer(i) => ValueDefinition::Value(Value::Integer(*i)),
+                Node::String(t, location) => string_value(t, location, env)?,
+                Node::RawString(t) => ValueDefinition::Value(Value::string(t)),
+                Node::Integer(i) => ValueDefinition::Value(Value::big_integer(i.clone())),
                 Node::Float(f) => ValueDefinition::Value(Value::Float(*f)),
+                Node::Duration(d) => ValueDefinition::Value(Value::Duration(parse_duration(d)?)),
+                Node::Size(d) => ValueDefinition::Value(Value::big_integer(parse_size(d)?)),
                 Node::GetAttr(node, label) => {
                     let parent = node.generate_argument(env)?;
                     match parent.unnamed_value()? {
@@ -280,8 +304,11 @@ impl Node {
                     _ => error("Unknown operator"),
                 },
 
-            Node::Glob(_) | Node::Label(_) | Node::Regex(_) | Node::Field(_) | Node::String(_) |
-            Node::Integer(_) | Node::Float(_) | Node::GetAttr(_, _) | Node::Path(_, _) | Node::Substitution(_) |
+            Node::String(t, location) => interpolation(t, location, env),
+
+            Node::Glob(_) | Node::Label(_) | Node::Regex(_) | Node::Field(_) | Node::RawString(_) |
+            Node::Integer(_) | Node::Float(_) | Node::Duration(_) | Node::Size(_) |
+            Node::GetAttr(_, _) | Node::Path(_, _) | Node::Substitution(_) |
             Node::Closure(_, _) | Node::File(_) => Ok(None),
         }
     }
@@ -324,7 +351,7 @@ impl Node {
 pub fn unescape(s: &str) -> String {
     let mut res = "".to_string();
     let mut was_backslash = false;
-    for c in s[1..s.len() - 1].chars() {
+    for c in s.chars() {
         if was_backslash {
             match c {
                 'n' => res += "\n",
@@ -344,6 +371,273 @@ pub fn unescape(s: &str) -> String {
     res
 }
 
+/**
+Remove the line break after the opening quotes of a multi-line string, the
+line holding the closing quotes if it is blank, and the indentation that all
+remaining non-blank lines have in common.
+*/
+pub fn strip_indentation(s: &str) -> String {
+    unindented(s).into_iter().map(|r| &s[r]).collect()
+}
+
+/**
+The byte ranges of a multi-line string that remain once the leading newline,
+the trailing line if it only holds whitespace and the indentation common to
+all lines have been removed.
+*/
+fn unindented(s: &str) -> Vec<Range<usize>> {
+    let start = if s.starts_with('\n') { 1 } else { 0 };
+    let mut lines = Vec::new();
+    let mut pos = start;
+    for line in s[start..].split('\n') {
+        lines.push(pos..pos + line.len());
+        pos += line.len() + 1;
+    }
+    if lines.len() > 1 && s[lines[lines.len() - 1].clone()].trim().is_empty() {
+        lines.pop();
+    }
+    let indentation = |l: &str| l.chars().take_while(|c| *c == ' ' || *c == '\t').count();
+    let common = lines.iter()
+        .map(|r| &s[r.clone()])
+        .filter(|l| !l.trim().is_empty())
+        .map(indentation)
+        .min()
+        .unwrap_or(0);
+    let last = lines.len().saturating_sub(1);
+    lines.iter()
+        .enumerate()
+        // Every line but the last keeps the newline that ends it
+        .map(|(idx, r)| r.start + common.min(indentation(&s[r.clone()]))..if idx < last { r.end + 1 } else { r.end })
+        .collect()
+}
+
+/**
+The contents of a raw string literal, i.e. r"..." or r"""...""".
+*/
+pub fn raw_string(s: &str) -> String {
+    if s.starts_with("r\"\"\"") {
+        strip_indentation(&s[4..s.len() - 3])
+    } else {
+        s[2..s.len() - 1].to_string()
+    }
+}
+
+enum StringPart {
+    Text(String),
+    /** The source code of an expression and the byte offset it starts at */
+    Expression(String, usize),
+}
+
+/**
+Split the contents of a string literal into escaped text and the source code
+of any interpolated ${...} expressions.
+*/
+fn string_parts(s: &str) -> CrushResult<Vec<StringPart>> {
+    let mut res = Vec::new();
+    let mut text = String::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => {
+                text.push(c);
+                if let Some((_, escaped)) = chars.next() {
+                    text.push(escaped);
+                }
+            }
+            '$' if chars.peek().map(|(_, c)| *c) == Some('{') => {
+                chars.next();
+                let start = chars.peek().map(|(idx, _)| *idx).unwrap_or(s.len());
+                let mut depth = 1;
+                let mut expression = String::new();
+                loop {
+                    match chars.next().map(|(_, c)| c) {
+                        None => return error("Unterminated interpolation in string"),
+                        Some('\\') => match chars.next().map(|(_, c)| c) {
+                            Some('"') => expression.push('"'),
+                            Some(escaped) => {
+                                expression.push('\\');
+                                expression.push(escaped);
+                            }
+                            None => return error("Unterminated interpolation in string"),
+                        },
+                        Some('{') => {
+                            depth += 1;
+                            expression.push('{');
+                        }
+                        Some('}') => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                            expression.push('}');
+                        }
+                        Some(c) => expression.push(c),
+                    }
+                }
+                res.push(StringPart::Text(std::mem::take(&mut text)));
+                res.push(StringPart::Expression(expression, start));
+            }
+            c => text.push(c),
+        }
+    }
+    res.push(StringPart::Text(text));
+    Ok(res)
+}
+
+/**
+The contents of a string literal, along with the byte ranges of the literal
+they were taken from. Multi-line literals have their indentation stripped.
+*/
+fn string_contents(literal: &str) -> (String, Vec<Range<usize>>) {
+    let ranges: Vec<Range<usize>> = if literal.starts_with("\"\"\"") {
+        unindented(&literal[3..literal.len() - 3])
+            .into_iter()
+            .map(|r| r.start + 3..r.end + 3)
+            .collect()
+    } else {
+        std::iter::once(1..literal.len() - 1).collect()
+    };
+    (ranges.iter().map(|r| &literal[r.clone()]).collect(), ranges)
+}
+
+/**
+The byte offset in a literal of a byte offset in its contents.
+*/
+fn literal_offset(ranges: &[Range<usize>], mut offset: usize) -> usize {
+    for r in ranges {
+        if offset < r.len() {
+            return r.start + offset;
+        }
+        offset -= r.len();
+    }
+    ranges.last().map(|r| r.end).unwrap_or(0)
+}
+
+/**
+A call to the format method of a string that builds the value of a string
+literal with interpolated expressions, or None if there are no expressions in
+the literal.
+*/
+fn interpolation(literal: &str, location: &Location, env: &Scope) -> CrushResult<Option<CommandInvocation>> {
+    let (contents, ranges) = string_contents(literal);
+    let parts = string_parts(&contents)?;
+    if parts.len() == 1 {
+        return Ok(None);
+    }
+    let mut format = String::new();
+    let mut arguments = Vec::new();
+    for part in parts {
+        match part {
+            StringPart::Text(t) => format.push_str(&unescape(&t).replace('{', "{{").replace('}', "}}")),
+            StringPart::Expression(e, offset) => {
+                let mut jobs = parse_at(
+                    &e,
+                    location.source(),
+                    location.start() + literal_offset(&ranges, offset),
+                    env)?;
+                if jobs.len() != 1 {
+                    return error("Expected exactly one job in string interpolation");
+                }
+                format.push_str("{}");
+                arguments.push(ArgumentDefinition::unnamed(ValueDefinition::JobDefinition(jobs.remove(0))));
+            }
+        }
+    }
+    Ok(Some(CommandInvocation::new(
+        ValueDefinition::GetAttr(
+            Box::from(ValueDefinition::Value(Value::string(&format))),
+            "format".to_string()),
+        arguments)))
+}
+
+fn string_value(literal: &str, location: &Location, env: &Scope) -> CrushResult<ValueDefinition> {
+    Ok(match interpolation(literal, location, env)? {
+        Some(invocation) => ValueDefinition::JobDefinition(Job::new(vec![invocation], false)),
+        None => ValueDefinition::Value(Value::string(&unescape(&string_contents(literal).0))),
+    })
+}
+
+/**
+The value of an integer literal, which may be hexadecimal, octal or binary.
+*/
+pub fn parse_integer(s: &str) -> BigInt {
+    let s = s.replace('_', "");
+    let (digits, radix) = match s.get(..2) {
+        Some("0x") => (&s[2..], 16),
+        Some("0o") => (&s[2..], 8),
+        Some("0b") => (&s[2..], 2),
+        _ => (s.as_str(), 10),
+    };
+    BigInt::parse_bytes(digits.as_bytes(), radix).unwrap_or_default()
+}
+
+/**
+A decimal number with an optional fraction multiplied by a unit, rounded
+towards zero.
+*/
+fn scaled(number: &str, unit: i128) -> Option<i128> {
+    let number = number.replace('_', "");
+    let (whole, fraction) = number.split_once('.').unwrap_or((&number, ""));
+    let digits = i128::from_str(&format!("{}{}", whole, fraction)).ok()?;
+    Some(digits.checked_mul(unit)? / 10i128.checked_pow(fraction.len() as u32)?)
+}
+
+fn split_unit(s: &str) -> (&str, &str) {
+    s.split_at(s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len()))
+}
+
+/**
+The value of a duration literal like 30s or 1h15m.
+*/
+fn parse_duration(s: &str) -> CrushResult<Duration> {
+    let mut nanoseconds: i128 = 0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let (number, tail) = split_unit(rest);
+        let end = tail.find(|c: char| c.is_ascii_digit()).unwrap_or(tail.len());
+        let unit = match &tail[..end] {
+            "ns" => 1,
+            "us" => 1_000,
+            "ms" => 1_000_000,
+            "s" => 1_000_000_000,
+            "m" => 60 * 1_000_000_000,
+            "h" => 60 * 60 * 1_000_000_000,
+            "d" => 24 * 60 * 60 * 1_000_000_000,
+            _ => return error("Invalid duration unit"),
+        };
+        nanoseconds = mandate(
+            scaled(number, unit).and_then(|n| nanoseconds.checked_add(n)),
+            "Duration literal is too long")?;
+        rest = &tail[end..];
+    }
+    match i64::try_from(nanoseconds) {
+        Ok(n) => Ok(Duration::nanoseconds(n)),
+        Err(_) => error("Duration literal is too long"),
+    }
+}
+
+/**
+The number of bytes of a size literal like 10MiB or 1.5GB.
+*/
+fn parse_size(s: &str) -> CrushResult<BigInt> {
+    let (number, unit) = split_unit(s);
+    let unit: i128 = match unit {
+        "B" => 1,
+        "kB" | "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        "TB" => 1_000_000_000_000,
+        "PB" => 1_000_000_000_000_000,
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        "TiB" => 1 << 40,
+        "PiB" => 1 << 50,
+        _ => return error("Invalid size unit"),
+    };
+    mandate(scaled(number, unit).map(BigInt::from), "Size literal is too large")
+}
+
 pub enum ParameterNode {
     Parameter(String, Option<Box<Node>>, Option<Node>),
     Named(String),
@@ -5,7 +5,11 @@ use std::io::{Error, Read, Write};
 use crossbeam::{Receiver, bounded, Sender};
 use std::fmt::{Debug, Formatter};
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::os::unix::io::{IntoRawFd, FromRawFd};
+use std::sync::{Arc, Mutex};
+use crate::util::compression::Compression;
+use crate::util::thread::build;
 
 struct ChannelReader {
     receiver: Receiver<Box<[u8]>>,
@@ -50,9 +54,10 @@ impl std::io::Read for ChannelReader {
                     self.buff = None;
                     Ok(res)
                 } else {
-                    dst.write_all(src)?;
-                    self.buff = Some(Box::from(&src[dst.len()..]));
-                    Ok(dst.len())
+                    let res = dst.len();
+                    dst.write_all(&src[..res])?;
+                    self.buff = Some(Box::from(&src[res..]));
+                    Ok(res)
                 }
             }
         }
@@ -107,21 +112,148 @@ impl BinaryReader for FileReader {
     }
 }
 
+/**
+Reads a compressed file. Cloning the reader opens the file again on the first
+read of the clone, and starts decompressing from the beginning.
+*/
+struct DecompressingReader {
+    path: PathBuf,
+    compression: Compression,
+    inner: Mutex<Option<Box<dyn Read + Send>>>,
+}
+
+impl DecompressingReader {
+    pub fn new(path: PathBuf, compression: Compression) -> CrushResult<DecompressingReader> {
+        let inner = DecompressingReader::open(&path, compression)?;
+        Ok(DecompressingReader { path, compression, inner: Mutex::new(Some(inner)) })
+    }
+
+    fn open(path: &Path, compression: Compression) -> CrushResult<Box<dyn Read + Send>> {
+        compression.decoder(to_crush_error(File::open(path))?)
+    }
+}
+
+impl Debug for DecompressingReader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.write_str("<decompressing reader>")
+    }
+}
+
+impl Read for DecompressingReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let inner = match self.inner.get_mut() {
+            Ok(inner) => inner,
+            Err(_) => return Err(Error::other("Poisoned reader")),
+        };
+        if inner.is_none() {
+            *inner = Some(DecompressingReader::open(&self.path, self.compression)
+                .map_err(|e| Error::other(e.message))?);
+        }
+        inner.as_mut().unwrap().read(buf)
+    }
+}
+
+impl BinaryReader for DecompressingReader {
+    fn clone(&self) -> Box<dyn BinaryReader + Send + Sync> {
+        Box::from(DecompressingReader {
+            path: self.path.clone(),
+            compression: self.compression,
+            inner: Mutex::new(None),
+        })
+    }
+}
+
+/**
+A reader whose data is produced by a function running in a separate thread.
+The thread is started on the first read, and each clone of the reader runs
+the function again. If the function fails, reading its data fails with the
+same error once all the data written before the failure has been read.
+*/
+type Producer = dyn Fn(&mut dyn Write) -> CrushResult<()> + Send + Sync;
+
+struct LazyReader {
+    producer: Arc<Producer>,
+    reader: Option<ChannelReader>,
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl LazyReader {
+    fn new(producer: Arc<Producer>) -> LazyReader {
+        LazyReader { producer, reader: None, error: Arc::from(Mutex::new(None)) }
+    }
+}
+
+impl Debug for LazyReader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.write_str("<lazy reader>")
+    }
+}
+
+impl Read for LazyReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.reader.is_none() {
+            let (s, r) = bounded(32);
+            let producer = self.producer.clone();
+            let error = self.error.clone();
+            build("binary:lazy").spawn(move || {
+                let mut writer = ChannelWriter { sender: s };
+                // The error is stored before the writer is dropped, so that
+                // it is there by the time the reader sees the end of the data
+                if let Err(e) = producer(&mut writer) {
+                    *error.lock().unwrap() = Some(e.message);
+                }
+            })?;
+            self.reader = Some(ChannelReader { receiver: r, buff: None });
+        }
+        let res = self.reader.as_mut().unwrap().read(buf)?;
+        if res == 0 && !buf.is_empty() {
+            if let Some(message) = self.error.lock().unwrap().take() {
+                return Err(Error::other(message));
+            }
+        }
+        Ok(res)
+    }
+}
+
+impl BinaryReader for LazyReader {
+    fn clone(&self) -> Box<dyn BinaryReader + Send + Sync> {
+        Box::from(LazyReader::new(self.producer.clone()))
+    }
+}
+
 impl dyn BinaryReader {
-    pub fn paths(mut files: Vec<PathBuf>) -> CrushResult<Box<dyn BinaryReader + Send + Sync>> {
+    /**
+    Read the specified files one after another. If decompress is true, files
+    with the extension of a known compression format are decompressed.
+    */
+    pub fn paths(mut files: Vec<PathBuf>, decompress: bool) -> CrushResult<Box<dyn BinaryReader + Send + Sync>> {
         if files.len() == 1 {
-            Ok(Box::from(FileReader::new(to_crush_error(File::open(files.remove(0)))?)))
+            <dyn BinaryReader>::path(files.remove(0), decompress)
         } else {
             let mut readers: Vec<Box<dyn BinaryReader + Send + Sync>> = Vec::new();
 
             for p in files.drain(..) {
-                let f = to_crush_error(File::open(p).map(|f| Box::from(FileReader::new(f))))?;
-                readers.push(f)
+                readers.push(<dyn BinaryReader>::path(p, decompress)?)
             }
             Ok(Box::from(MultiReader { inner: VecDeque::from(readers) }))
         }
     }
 
+    fn path(path: PathBuf, decompress: bool) -> CrushResult<Box<dyn BinaryReader + Send + Sync>> {
+        match Compression::from_path(&path) {
+            Some(compression) if decompress => Ok(Box::from(DecompressingReader::new(path, compression)?)),
+            _ => Ok(Box::from(FileReader::new(to_crush_error(File::open(path))?))),
+        }
+    }
+
+    pub fn lazy(producer: impl Fn(&mut dyn Write) -> CrushResult<()> + Send + Sync + 'static) -> Box<dyn BinaryReader + Send + Sync> {
+        Box::from(LazyReader::new(Arc::from(producer)))
+    }
+
+    pub fn pipe<T: IntoRawFd>(pipe: T) -> Box<dyn BinaryReader + Send + Sync> {
+        Box::from(FileReader::new(unsafe { File::from_raw_fd(pipe.into_raw_fd()) }))
+    }
+
     pub fn vec(vec: &Vec<u8>) -> Box<dyn BinaryReader + Send + Sync> {
         Box::from(VecReader { vec: vec.clone(), offset: 0 })
     }
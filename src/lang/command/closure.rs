@@ -1,5 +1,5 @@
 use crate::lang::errors::{CrushResult, argument_error, error, mandate};
-use crate::lang::argument::{Argument, ArgumentDefinition, ArgumentType};
+use crate::lang::argument::{Argument, ArgumentDefinition, ArgumentType, ArgumentDescription};
 use crate::lang::command::{Parameter, Command, BoundCommand, CrushCommand, OutputType};
 use crate::lang::scope::Scope;
 use std::collections::HashMap;
@@ -7,6 +7,7 @@ use crate::lang::value::{Value, ValueType, ValueDefinition};
 use crate::lang::list::List;
 use crate::lang::dict::Dict;
 use crate::lang::job::Job;
+use crate::lang::{execute, job_table};
 use crate::lang::stream::{empty_channel, black_hole};
 use crate::lang::execution_context::{ExecutionContext, CompileContext, JobContext};
 use crate::lang::help::Help;
@@ -48,8 +49,22 @@ impl CrushCommand for Closure {
             let last = idx == job_definitions.len() - 1;
             let input = if first { context.input.clone() } else { empty_channel() };
             let output = if last { context.output.clone() } else { black_hole() };
-            let job = job_definition.invoke(JobContext::new(input, output, env.clone(), context.printer.clone()))?;
-            job.join(&context.printer);
+            if job_definition.is_background() {
+                let id = job_table::create(job_definition.to_string(), true);
+                match job_definition.invoke(JobContext::new(input, output, env.clone(), context.printer.clone(), id)) {
+                    Ok(job) => job_table::detach(id, job, &context.printer)?,
+                    Err(e) => {
+                        job_table::remove(id);
+                        return Err(e);
+                    }
+                }
+            } else {
+                let job = job_definition.invoke(JobContext::new(input, output, env.clone(), context.printer.clone(), context.job_id))?;
+                match job.join(&context.printer) {
+                    Err(e) if last || execute::fail_on_error(&env) => return Err(e),
+                    res => context.printer.handle_error(res),
+                }
+            }
             if env.is_stopped() {
                 return Ok(());
             }
@@ -92,6 +107,19 @@ impl CrushCommand for Closure {
     fn output(&self, input: &OutputType) -> Option<&ValueType> {
         None
     }
+
+    fn arguments(&self) -> Vec<ArgumentDescription> {
+        self.signature.iter()
+            .flatten()
+            .filter_map(|p| match p {
+                Parameter::Parameter(name, _, _) => Some(ArgumentDescription {
+                    name: name.to_string(),
+                    allowed_values: None,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 struct ClosureSerializer<'a> {
@@ -184,6 +212,7 @@ impl<'a> ClosureSerializer<'a> {
         for c in job.commands() {
             s.commands.push(self.command(c)?);
         }
+        s.background = job.is_background();
         Ok(s)
     }
 
@@ -328,7 +357,8 @@ impl<'a> ClosureDeserializer<'a> {
         Ok(Job::new(
             s.commands.iter()
                 .map(|c| self.command(c))
-                .collect::<CrushResult<Vec<_>>>()?))
+                .collect::<CrushResult<Vec<_>>>()?,
+            s.background))
     }
 
 
@@ -392,9 +422,7 @@ impl<'a> ClosureDeserializer<'a> {
                         .map(|j| self.job(j))
                         .collect::<CrushResult<Vec<_>>>()?),
             model::value_definition::ValueDefinition::Job(j) =>
-                ValueDefinition::JobDefinition(Job::new(j.commands.iter()
-                    .map(|c| self.command(c))
-                    .collect::<CrushResult<Vec<_>>>()?)),
+                ValueDefinition::JobDefinition(self.job(j)?),
             model::value_definition::ValueDefinition::Label(s) =>
                 ValueDefinition::Label(s.clone()),
             model::value_definition::ValueDefinition::GetAttr(a) =>
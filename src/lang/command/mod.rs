@@ -2,7 +2,7 @@ mod closure;
 
 use crate::lang::errors::{CrushResult, error};
 use std::fmt::Formatter;
-use crate::lang::{argument::ArgumentDefinition};
+use crate::lang::argument::{ArgumentDefinition, ArgumentDescription};
 use crate::lang::scope::Scope;
 use crate::lang::job::Job;
 use crate::lang::value::{ValueDefinition, Value, ValueType};
@@ -50,6 +50,7 @@ pub trait CrushCommand: Help {
     fn serialize(&self, elements: &mut Vec<Element>, state: &mut SerializationState) -> CrushResult<usize>;
     fn bind(&self, this: Value) -> Command;
     fn output<'a>(&'a self, input: &'a OutputType) -> Option<&'a ValueType>;
+    fn arguments(&self) -> Vec<ArgumentDescription>;
 }
 
 pub trait TypeMap {
@@ -79,7 +80,7 @@ impl TypeMap for OrderedMap<String, Command> {
         self.insert(path[path.len() - 1].to_string(),
                     CrushCommand::command(
                         call, can_block, path.iter().map(|e| e.to_string()).collect(),
-                        signature, short_help, long_help, output),
+                        signature, short_help, long_help, output, vec![]),
         );
     }
 }
@@ -92,6 +93,7 @@ struct SimpleCommand {
     short_help: &'static str,
     long_help: Option<&'static str>,
     output: OutputType,
+    arguments: Vec<ArgumentDescription>,
 }
 
 struct ConditionCommand {
@@ -125,8 +127,9 @@ impl dyn CrushCommand {
         short_help: &'static str,
         long_help: Option<&'static str>,
         output: OutputType,
+        arguments: Vec<ArgumentDescription>,
     ) -> Command {
-        Box::from(SimpleCommand { call, can_block, full_name, signature, short_help, long_help, output })
+        Box::from(SimpleCommand { call, can_block, full_name, signature, short_help, long_help, output, arguments })
     }
 
     pub fn condition(
@@ -190,6 +193,7 @@ impl CrushCommand for SimpleCommand {
             short_help: self.short_help,
             long_help: self.long_help,
             output: self.output.clone(),
+            arguments: self.arguments.clone(),
         })
     }
 
@@ -217,6 +221,10 @@ impl CrushCommand for SimpleCommand {
     fn output<'a>(&'a self, input: &'a OutputType) -> Option<&'a ValueType> {
         self.output.calculate(input)
     }
+
+    fn arguments(&self) -> Vec<ArgumentDescription> {
+        self.arguments.clone()
+    }
 }
 
 impl Help for SimpleCommand {
@@ -300,6 +308,10 @@ impl CrushCommand for ConditionCommand {
     fn output(&self, input: &OutputType) -> Option<&ValueType> {
         None
     }
+
+    fn arguments(&self) -> Vec<ArgumentDescription> {
+        vec![]
+    }
 }
 
 impl Help for ConditionCommand {
@@ -407,6 +419,10 @@ impl CrushCommand for BoundCommand {
     fn output<'a>(&'a self, input: &'a OutputType) -> Option<&'a ValueType> {
         self.command.output(input)
     }
+
+    fn arguments(&self) -> Vec<ArgumentDescription> {
+        self.command.arguments()
+    }
 }
 
 impl Help for BoundCommand {
@@ -2,6 +2,7 @@ use crate::lang::{execution_context::ExecutionContext, job::JobJoinHandle, comma
 use crate::lang::{argument::ArgumentDefinition, argument::ArgumentVecCompiler, value::Value};
 use crate::lang::scope::Scope;
 use crate::lang::errors::{error, CrushResult, Kind};
+use crate::lang::source::Location;
 use crate::util::thread::{handle, build};
 use std::path::PathBuf;
 use crate::lang::execution_context::{JobContext, CompileContext};
@@ -11,6 +12,23 @@ use std::ops::Deref;
 pub struct CommandInvocation {
     command: ValueDefinition,
     arguments: Vec<ArgumentDefinition>,
+    location: Option<Location>,
+}
+
+/**
+The name and source location of a command invocation, used to tell the user
+where an error happened.
+*/
+#[derive(Clone)]
+struct Origin {
+    name: String,
+    location: Option<Location>,
+}
+
+impl Origin {
+    fn annotate<T>(&self, result: CrushResult<T>) -> CrushResult<T> {
+        result.map_err(|e| e.with_command(&self.name, self.location.as_ref()))
+    }
 }
 
 fn resolve_external_command(name: &str, env: &Scope) -> CrushResult<Option<PathBuf>> {
@@ -42,7 +60,23 @@ fn arg_can_block(local_arguments: &Vec<ArgumentDefinition>, context: &mut Compil
 
 impl CommandInvocation {
     pub fn new(command: ValueDefinition, arguments: Vec<ArgumentDefinition>) -> CommandInvocation {
-        CommandInvocation { command, arguments }
+        CommandInvocation { command, arguments, location: None }
+    }
+
+    pub fn with_location(mut self, location: Location) -> CommandInvocation {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    fn origin(&self) -> Origin {
+        Origin {
+            name: self.command.to_string(),
+            location: self.location.clone(),
+        }
     }
 
     pub fn as_string(&self) -> Option<String> {
@@ -117,31 +151,33 @@ impl CommandInvocation {
     }
 
     pub fn invoke(&self, context: JobContext) -> CrushResult<JobJoinHandle> {
-        match self.command.compile_internal(&mut context.compile_context(), false) {
+        let origin = self.origin();
+        let res = match self.command.compile_internal(&mut context.compile_context(), false) {
             Ok((this, value)) => {
-                invoke_value(this, value, self.arguments.clone(), context)
+                invoke_value(this, value, self.arguments.clone(), context, &origin)
             }
             Err(err) => {
                 if err.kind == Kind::BlockError {
                     let cmd = self.command.clone();
                     let arguments = self.arguments.clone();
+                    let origin = origin.clone();
                     Ok(handle(build(self.command.to_string().as_str()).spawn(
                         move || {
-                            match cmd.clone().compile_unbound(&mut context.compile_context()) {
+                            let printer = context.printer.clone();
+                            origin.annotate(match cmd.clone().compile_unbound(&mut context.compile_context()) {
                                 Ok((this, value)) =>
-                                    context.printer.handle_error(
-                                        invoke_value(this, value, arguments, context.clone())),
+                                    invoke_value(this, value, arguments, context, &origin),
 
                                 _ =>
-                                    context.printer.handle_error(
-                                        try_external_command(cmd, arguments, context.clone())),
-                            }
+                                    try_external_command(cmd, arguments, context, &origin),
+                            }.and_then(|h| h.join(&printer)))
                         })))
                 } else {
-                    try_external_command(self.command.clone(), self.arguments.clone(), context)
+                    try_external_command(self.command.clone(), self.arguments.clone(), context, &origin)
                 }
             }
-        }
+        };
+        origin.annotate(res)
     }
 }
 
@@ -149,10 +185,11 @@ fn invoke_value(
     this: Option<Value>,
     value: Value,
     local_arguments: Vec<ArgumentDefinition>,
-    context: JobContext) -> CrushResult<JobJoinHandle> {
+    context: JobContext,
+    origin: &Origin) -> CrushResult<JobJoinHandle> {
     match value {
         Value::Command(command) =>
-            invoke_command(command, this, local_arguments, context),
+            invoke_command(command, this, local_arguments, context, origin),
         Value::File(f) =>
             if local_arguments.len() == 0 {
                 let meta = f.metadata();
@@ -161,13 +198,15 @@ fn invoke_value(
                         context.env.global_static_cmd(vec!["global", "traversal", "cd"])?,
                         None,
                         vec![ArgumentDefinition::unnamed(ValueDefinition::Value(Value::File(f)))],
-                        context)
+                        context,
+                        origin)
                 } else {
                     invoke_command(
                         context.env.global_static_cmd(vec!["global", "io", "val"])?,
                         None,
                         vec![ArgumentDefinition::unnamed(ValueDefinition::Value(Value::File(f)))],
-                        context)
+                        context,
+                        origin)
                 }
             } else {
                 error(format!("Not a command {}", f.to_str().unwrap_or("<invalid filename>")).as_str())
@@ -179,13 +218,15 @@ fn invoke_value(
                         context.env.global_static_cmd(vec!["global", "io", "val"])?,
                         None,
                         vec![ArgumentDefinition::unnamed(ValueDefinition::Value(Value::Type(t)))],
-                        context),
+                        context,
+                        origin),
                 Some(call) =>
                     invoke_command(
                         call.as_ref().clone(),
                         Some(Value::Type(t)),
                         local_arguments,
-                        context),
+                        context,
+                        origin),
             }
         }
         _ =>
@@ -194,7 +235,8 @@ fn invoke_value(
                     context.env.global_static_cmd(vec!["global", "io", "val"])?,
                     None,
                     vec![ArgumentDefinition::unnamed(ValueDefinition::Value(value))],
-                    context)
+                    context,
+                    origin)
             } else {
                 error(format!("Not a command {}", value.to_string()).as_str())
             }
@@ -205,27 +247,24 @@ fn invoke_command(
     action: Command,
     this: Option<Value>,
     local_arguments: Vec<ArgumentDefinition>,
-    context: JobContext) -> CrushResult<JobJoinHandle> {
+    context: JobContext,
+    origin: &Origin) -> CrushResult<JobJoinHandle> {
     if !action.can_block(&local_arguments, &mut context.compile_context()) && !arg_can_block(&local_arguments, &mut context.compile_context()) {
         let new_context = CommandInvocation::execution_context(
             local_arguments,
             this,
             context.clone())?;
-        context.printer.handle_error(action.invoke(new_context));
-        Ok(JobJoinHandle::Many(vec![]))
+        Ok(JobJoinHandle::Done(origin.annotate(action.invoke(new_context))))
     } else {
+        let origin = origin.clone();
         Ok(handle(build(action.name()).spawn(
             move || {
-                let res = CommandInvocation::execution_context(
-                    local_arguments,
-                    this,
-                    context.clone());
-                if let Ok(ctx) = res {
-                    let p = ctx.printer.clone();
-                    p.handle_error(action.invoke(ctx));
-                } else {
-                    context.printer.handle_error(res);
-                }
+                origin.annotate(
+                    CommandInvocation::execution_context(
+                        local_arguments,
+                        this,
+                        context)
+                        .and_then(|ctx| action.invoke(ctx)))
             })))
     }
 }
@@ -233,7 +272,8 @@ fn invoke_command(
 fn try_external_command(
     def: ValueDefinition,
     mut arguments: Vec<ArgumentDefinition>,
-    context: JobContext) -> CrushResult<JobJoinHandle> {
+    context: JobContext,
+    origin: &Origin) -> CrushResult<JobJoinHandle> {
     let (cmd, sub) = match def {
         ValueDefinition::Label(str) => (str, None),
         ValueDefinition::GetAttr(parent, sub) =>
@@ -255,18 +295,25 @@ fn try_external_command(
                     1,
                     ArgumentDefinition::unnamed(ValueDefinition::Value(Value::string(subcmd.as_ref()))));
             }
-            let call = CommandInvocation {
-                command: ValueDefinition::Value(Value::Command(
-                    context.env.global_static_cmd(vec!["global", "control", "cmd"])?)),
+            invoke_command(
+                context.env.global_static_cmd(vec!["global", "control", "cmd"])?,
+                None,
                 arguments,
-            };
-            call.invoke(context)
+                context,
+                origin)
         }
     }
 }
 
 impl ToString for CommandInvocation {
     fn to_string(&self) -> String {
-        self.command.to_string()
+        if self.arguments.is_empty() {
+            self.command.to_string()
+        } else {
+            format!(
+                "{} {}",
+                self.command.to_string(),
+                self.arguments.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(" "))
+        }
     }
 }
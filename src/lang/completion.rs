@@ -0,0 +1,275 @@
+use crate::lang::errors::{CrushResult, to_crush_error};
+use crate::lang::scope::Scope;
+use crate::lang::value::Value;
+use crate::lang::argument::ArgumentDescription;
+use crate::util::glob::Glob;
+use ordered_map::OrderedMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq)]
+pub struct Completion {
+    pub display: String,
+    pub replacement: String,
+}
+
+impl Completion {
+    fn new(display: &str, replacement: String) -> Completion {
+        Completion {
+            display: display.to_string(),
+            replacement,
+        }
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c == '|' || c == ';' || c == '(' || c == '{' || c == '&' || c == '\n'
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !is_separator(c) && c != ')' && c != '}' && c != '[' && c != ']' && c != '"'
+}
+
+/**
+The byte offset of the start of the command that the cursor is in, skipping
+over any closures and subshells before the cursor that have been closed.
+*/
+fn command_start(line: &str) -> usize {
+    let mut depth = 0;
+    for (idx, c) in line.char_indices().rev() {
+        match c {
+            ')' | '}' => depth += 1,
+            '(' | '{' if depth > 0 => depth -= 1,
+            c if depth == 0 && is_separator(c) => return idx + c.len_utf8(),
+            _ => {}
+        }
+    }
+    0
+}
+
+fn members(value: &Value) -> CrushResult<Vec<String>> {
+    Ok(match value {
+        Value::Scope(scope) => {
+            let mut map = OrderedMap::new();
+            scope.dump(&mut map)?;
+            let mut res = map.keys().map(|k| k.to_string()).collect::<Vec<String>>();
+            res.append(&mut value.fields());
+            res
+        }
+        _ => value.fields(),
+    })
+}
+
+fn resolve(path: &[&str], scope: &Scope) -> CrushResult<Option<Value>> {
+    let mut value = match scope.get(path[0])? {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    for name in &path[1..] {
+        value = match value.field(name)? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+    }
+    Ok(Some(value))
+}
+
+fn complete_variable(word: &str, scope: &Scope, res: &mut Vec<Completion>) -> CrushResult<()> {
+    let mut map = OrderedMap::new();
+    scope.dump(&mut map)?;
+    for name in map.keys() {
+        if name.starts_with(word) {
+            res.push(Completion::new(name, name.to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn complete_member(word: &str, scope: &Scope, res: &mut Vec<Completion>) -> CrushResult<()> {
+    let split = word.rfind(':').unwrap();
+    let path = word[..split].split(':').collect::<Vec<&str>>();
+    let prefix = &word[split + 1..];
+    if let Some(value) = resolve(&path, scope)? {
+        for member in members(&value)? {
+            if member.starts_with(prefix) {
+                res.push(Completion::new(&member, format!("{}:{}", &word[..split], member)));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn complete_file(word: &str, cwd: &Path, res: &mut Vec<Completion>) -> CrushResult<()> {
+    if word.contains('%') || word.contains('?') {
+        let mut files = Vec::new();
+        Glob::new(word).glob_files(cwd, &mut files)?;
+        for file in files {
+            let name = file.strip_prefix(cwd).unwrap_or(&file).to_string_lossy().to_string();
+            res.push(Completion::new(&name, name.clone()));
+        }
+        return Ok(());
+    }
+
+    let (dir, prefix) = match word.rfind('/') {
+        Some(idx) => (&word[..idx + 1], &word[idx + 1..]),
+        None => ("", word),
+    };
+    let full_dir = if dir.starts_with('/') { PathBuf::from(dir) } else { cwd.join(dir) };
+    let entries = match full_dir.read_dir() {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()),
+    };
+    for entry in entries {
+        let entry = to_crush_error(entry)?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+            continue;
+        }
+        let suffix = if entry.path().is_dir() { "/" } else { "" };
+        res.push(Completion::new(
+            &format!("{}{}", name, suffix),
+            format!("{}{}{}", dir, name, suffix)));
+    }
+    Ok(())
+}
+
+fn command_arguments(command: &str, scope: &Scope) -> CrushResult<Vec<ArgumentDescription>> {
+    Ok(match resolve(&command.split(':').collect::<Vec<&str>>(), scope)? {
+        Some(Value::Command(command)) => command.arguments(),
+        _ => vec![],
+    })
+}
+
+fn complete_argument(word: &str, arguments: &[ArgumentDescription], res: &mut Vec<Completion>) -> bool {
+    match word.find('=') {
+        Some(idx) => {
+            let (name, value) = (&word[..idx], &word[idx + 1..]);
+            for argument in arguments {
+                if argument.name == name {
+                    if let Some(allowed) = &argument.allowed_values {
+                        for v in allowed {
+                            if v.starts_with(value) || v.trim_start_matches('"').starts_with(value) {
+                                res.push(Completion::new(v, format!("{}={}", name, v)));
+                            }
+                        }
+                        return true;
+                    }
+                }
+            }
+            false
+        }
+        None => {
+            for argument in arguments {
+                if argument.name.starts_with(word) {
+                    res.push(Completion::new(&format!("{}=", argument.name), format!("{}=", argument.name)));
+                }
+            }
+            false
+        }
+    }
+}
+
+/**
+Find the possible completions of the word under the cursor. Returns the byte
+offset of the start of the word, which each completion replaces.
+
+The first word of a command is completed as a variable name, anything else as
+a named argument of the command, a variable or a file. Words containing a colon
+are completed as members of a namespace or a value.
+*/
+pub fn complete(line: &str, pos: usize, scope: &Scope, cwd: &Path) -> CrushResult<(usize, Vec<Completion>)> {
+    let before = &line[..pos];
+    let start = before.char_indices().rev()
+        .find(|(_, c)| !is_word_char(*c))
+        .map(|(idx, c)| idx + c.len_utf8())
+        .unwrap_or(0);
+    let word = &before[start..];
+    let command = &before[command_start(&before[..start])..start];
+    let mut res = Vec::new();
+
+    match command.split_whitespace().next() {
+        None => {
+            if word.contains(':') {
+                complete_member(word, scope, &mut res)?;
+            } else if word.contains('/') {
+                complete_file(word, cwd, &mut res)?;
+            } else {
+                complete_variable(word, scope, &mut res)?;
+            }
+        }
+        Some(command) => {
+            let arguments = command_arguments(command, scope)?;
+            if !complete_argument(word, &arguments, &mut res) {
+                let (offset, value) = match word.find('=') {
+                    Some(idx) => (idx + 1, &word[idx + 1..]),
+                    None => (0, word),
+                };
+                let mut values = Vec::new();
+                if value.contains(':') {
+                    complete_member(value, scope, &mut values)?;
+                } else {
+                    if !value.contains('/') {
+                        complete_variable(value, scope, &mut values)?;
+                    }
+                    complete_file(value, cwd, &mut values)?;
+                }
+                res.extend(values.into_iter().map(|c| Completion {
+                    display: c.display,
+                    replacement: format!("{}{}", &word[..offset], c.replacement),
+                }));
+            }
+        }
+    }
+
+    res.sort_by(|a, b| a.replacement.cmp(&b.replacement));
+    res.dedup_by(|a, b| a.replacement == b.replacement);
+    Ok((start, res))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::command::{CrushCommand, OutputType};
+    use crate::lang::execution_context::ExecutionContext;
+    use crate::lang::r#struct::Struct;
+
+    fn noop(_context: ExecutionContext) -> CrushResult<()> {
+        Ok(())
+    }
+
+    fn scope() -> Scope {
+        let scope = Scope::create_root();
+        scope.declare("http", Value::Command(CrushCommand::command(
+            noop, false, vec!["http".to_string()], "http", "", None, OutputType::Unknown,
+            vec![
+                ArgumentDescription { name: "method".to_string(), allowed_values: Some(vec!["\"get\"".to_string(), "\"post\"".to_string()]) },
+                ArgumentDescription { name: "header".to_string(), allowed_values: None },
+            ]))).unwrap();
+        scope.declare("host", Value::Struct(Struct::new(
+            vec![("name".to_string(), Value::string("localhost"))], None))).unwrap();
+        scope
+    }
+
+    fn replacements(line: &str) -> Vec<String> {
+        complete(line, line.len(), &scope(), Path::new("/nonexistent"))
+            .unwrap().1.into_iter().map(|c| c.replacement).collect()
+    }
+
+    #[test]
+    fn complete_command_name() {
+        assert_eq!(replacements("ht"), vec!["http"]);
+        assert_eq!(replacements("echo 1 | ht"), vec!["http"]);
+        assert_eq!(replacements("echo {h"), vec!["host", "http"]);
+    }
+
+    #[test]
+    fn complete_member_name() {
+        assert_eq!(replacements("echo host:na"), vec!["host:name"]);
+    }
+
+    #[test]
+    fn complete_named_argument() {
+        assert_eq!(replacements("http me"), vec!["method="]);
+        assert_eq!(replacements("(echo 1) | http me"), vec!["method="]);
+        assert_eq!(replacements("http method=p"), vec!["method=\"post\""]);
+    }
+}
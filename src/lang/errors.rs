@@ -1,66 +1,123 @@
 use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use crate::lang::errors::Kind::*;
+use crate::lang::source::Location;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Kind {
-//    ParseError,
+    ParseError,
     InvalidArgument,
     InvalidData,
     GenericError,
     BlockError,
     SendError,
+    UserError,
 }
 
-#[derive(Debug)]
+impl Display for Kind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ParseError => "parse_error",
+            InvalidArgument => "invalid_argument",
+            InvalidData => "invalid_data",
+            GenericError => "generic_error",
+            BlockError => "block_error",
+            SendError => "send_error",
+            UserError => "user_error",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct CrushError {
     pub kind: Kind,
     pub message: String,
+    /// The name of the command that failed
+    pub command: Option<String>,
+    /// The source code of the command that failed
+    pub location: Option<Location>,
+    /// The underlying error, if this error was caused by another one
+    pub cause: Option<Box<CrushError>>,
+}
+
+impl CrushError {
+    pub fn new(kind: Kind, message: &str) -> CrushError {
+        CrushError {
+            kind,
+            message: message.to_string(),
+            command: None,
+            location: None,
+            cause: None,
+        }
+    }
+
+    /**
+    Record which command failed and where it is in the source, unless that is
+    already known. Errors pass through every command that a failing closure
+    was called from, and the innermost command is the interesting one.
+    */
+    pub fn with_command(mut self, command: &str, location: Option<&Location>) -> CrushError {
+        if self.command.is_none() && self.location.is_none() {
+            self.command = Some(command.to_string());
+            self.location = location.cloned();
+        }
+        self
+    }
+
+    pub fn with_location(mut self, location: Location) -> CrushError {
+        if self.location.is_none() {
+            self.location = Some(location);
+        }
+        self
+    }
+
+    pub fn with_cause(mut self, cause: CrushError) -> CrushError {
+        self.cause = Some(Box::from(cause));
+        self
+    }
 }
 
 pub type CrushResult<T> = Result<T, CrushError>;
 
 pub fn block_error<T>() -> Result<T, CrushError> {
-    Err(CrushError {
-        message: String::from("Internal error: Tried to call blocking code in a thread that may not block"),
-        kind: BlockError,
-    })
+    Err(CrushError::new(BlockError, "Internal error: Tried to call blocking code in a thread that may not block"))
 }
 
 pub fn send_error<T>() -> Result<T, CrushError> {
-    Err(CrushError {
-        message: String::from("Tried to send data to a command that is no longer listening. This is almost normal behaviour and can be safely ignored."),
-        kind: SendError,
-    })
+    Err(CrushError::new(SendError, "Tried to send data to a command that is no longer listening. This is almost normal behaviour and can be safely ignored."))
 }
 
 pub fn argument_error<T>(message: &str) -> Result<T, CrushError> {
-    Err(CrushError {
-        message: String::from(message),
-        kind: InvalidArgument,
-    })
+    Err(CrushError::new(InvalidArgument, message))
 }
 
 pub fn data_error<T>(message: &str) -> Result<T, CrushError> {
-    Err(CrushError {
-        message: String::from(message),
-        kind: InvalidData,
-    })
+    Err(CrushError::new(InvalidData, message))
 }
 
 pub fn error<T>(message: &str) -> Result<T, CrushError> {
-    Err(CrushError {
-        message: String::from(message),
-        kind: GenericError,
-    })
+    Err(CrushError::new(GenericError, message))
+}
+
+fn from_error(e: &dyn Error) -> CrushError {
+    let err = CrushError::new(GenericError, &e.to_string());
+    match e.source() {
+        Some(cause) => err.with_cause(from_error(cause)),
+        None => err,
+    }
 }
 
 pub fn to_crush_error<T, E: Error>(result: Result<T, E>) -> Result<T, CrushError> {
     match result {
         Ok(v) => Ok(v),
-        Err(e) => error(e.to_string().as_str()),
+        Err(e) => Err(from_error(&e)),
     }
 }
 
+pub fn user_error<T>(message: &str) -> Result<T, CrushError> {
+    Err(CrushError::new(UserError, message))
+}
+
 pub fn mandate<T>(result: Option<T>, msg: &str) -> Result<T, CrushError> {
     match result {
         Some(v) => Ok(v),
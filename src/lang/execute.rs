@@ -8,11 +8,14 @@ use crate::lang::stream::{empty_channel, ValueSender, channels};
 use std::path::Path;
 use crate::lang::serialization::{deserialize, serialize};
 use crate::lang::value::Value;
+use crate::lang::job_table;
+use crate::lang::source::Source;
 use std::io::Write;
+use std::sync::Arc;
 
 pub fn file(global_env: Scope, filename: &Path, printer: &Printer, output: &ValueSender) -> CrushResult<()> {
     let cmd = to_crush_error(fs::read_to_string(filename))?;
-    string(global_env, &cmd.as_str(), printer, output);
+    source(global_env, &Source::file(filename, cmd), printer, output);
     Ok(())
 }
 
@@ -32,7 +35,8 @@ pub fn pup(env: Scope, buf: &Vec<u8>, printer: &Printer) -> CrushResult<()> {
                         Ok(())
                     }))?;
 
-            cmd.invoke(
+            let job_id = job_table::create("pup".to_string(), false);
+            let res = cmd.invoke(
                 ExecutionContext {
                     input: empty_channel(),
                     output: snd,
@@ -40,10 +44,14 @@ pub fn pup(env: Scope, buf: &Vec<u8>, printer: &Printer) -> CrushResult<()> {
                     env,
                     this: None,
                     printer: printer.clone(),
+                    job_id,
                 }
-            )?;
+            );
+            let joined = t.join();
+            job_table::remove(job_id);
+            res?;
 
-            match t.join() {
+            match joined {
                 Ok(_) => Ok(()),
                 Err(_) => argument_error("Error while waiting for output"),
             }
@@ -52,21 +60,65 @@ pub fn pup(env: Scope, buf: &Vec<u8>, printer: &Printer) -> CrushResult<()> {
     }
 }
 
+/**
+Whether a failed job aborts the rest of the script or closure it is part of,
+which is the case if cmd_fail_on_error is true or if it runs inside the body
+of a try command.
+*/
+pub fn fail_on_error(env: &Scope) -> bool {
+    if env.stops_on_error() {
+        return true;
+    }
+    match env.get("cmd_fail_on_error") {
+        Ok(Some(Value::Bool(b))) => b,
+        _ => false,
+    }
+}
+
 pub fn string(global_env: Scope, s: &str, printer: &Printer, output: &ValueSender) {
-    match parse(s, &global_env) {
-        Ok(jobs) => {
-            for job_definition in jobs {
-                match job_definition.invoke(JobContext::new(
-                    empty_channel(), output.clone(), global_env.clone(), printer.clone())) {
-                    Ok(handle) => {
-                        handle.join(&printer);
-                    }
-                    Err(e) => printer.crush_error(e),
+    source(global_env, &Source::string(s), printer, output)
+}
+
+pub fn source(global_env: Scope, s: &Arc<Source>, printer: &Printer, output: &ValueSender) {
+    if let Err(error) = run(global_env, s, printer, output, false) {
+        printer.crush_error(error);
+    }
+}
+
+/**
+Run the code of a module. Unlike for other sources, the first job that fails
+stops the module and its error is returned, so that loading the module fails.
+*/
+pub fn module(global_env: Scope, s: &Arc<Source>, printer: &Printer, output: &ValueSender) -> CrushResult<()> {
+    run(global_env, s, printer, output, true)
+}
+
+fn run(global_env: Scope, s: &Arc<Source>, printer: &Printer, output: &ValueSender, stop_on_error: bool) -> CrushResult<()> {
+    for job_definition in parse(s, &global_env)? {
+        let id = job_table::create(job_definition.to_string(), job_definition.is_background());
+        let res = match job_definition.invoke(JobContext::new(
+            empty_channel(), output.clone(), global_env.clone(), printer.clone(), id)) {
+            Ok(handle) => {
+                if job_definition.is_background() {
+                    job_table::detach(id, handle, printer)
+                } else {
+                    job_table::wait(id, handle, printer)
                 }
             }
-        }
-        Err(error) => {
-            printer.crush_error(error);
+            Err(e) => {
+                job_table::remove(id);
+                Err(e)
+            }
+        };
+        if let Err(e) = res {
+            if stop_on_error {
+                return Err(e);
+            }
+            printer.crush_error(e);
+            if fail_on_error(&global_env) {
+                break;
+            }
         }
     }
+    Ok(())
 }
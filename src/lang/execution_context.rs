@@ -15,6 +15,7 @@ use chrono::{DateTime, Local, Duration};
 use crate::lang::table::{Table, TableReader};
 use crate::lang::printer::Printer;
 use crate::lang::job::JobJoinHandle;
+use crate::lang::job_table::JobId;
 use crate::lang::binary::{BinaryReader, binary_channel};
 use std::io::Write;
 use std::fs::File;
@@ -147,17 +148,20 @@ pub struct CompileContext {
     pub dependencies: Vec<JobJoinHandle>,
     pub env: Scope,
     pub printer: Printer,
+    pub job_id: JobId,
 }
 
 impl CompileContext {
     pub fn new(
         env: Scope,
         printer: Printer,
+        job_id: JobId,
     ) -> CompileContext {
         CompileContext {
             dependencies: Vec::new(),
             env,
             printer,
+            job_id,
         }
     }
 
@@ -166,7 +170,7 @@ impl CompileContext {
         input: ValueReceiver,
         output: ValueSender,
     ) -> JobContext {
-        JobContext::new(input, output, self.env.clone(), self.printer.clone())
+        JobContext::new(input, output, self.env.clone(), self.printer.clone(), self.job_id)
     }
 
     pub fn with_scope(
@@ -176,6 +180,7 @@ impl CompileContext {
             dependencies: vec![],
             env: env.clone(),
             printer: self.printer.clone(),
+            job_id: self.job_id,
         }
     }
 }
@@ -186,6 +191,7 @@ pub struct JobContext {
     pub output: ValueSender,
     pub env: Scope,
     pub printer: Printer,
+    pub job_id: JobId,
 }
 
 impl JobContext {
@@ -194,12 +200,14 @@ impl JobContext {
         output: ValueSender,
         env: Scope,
         printer: Printer,
+        job_id: JobId,
     ) -> JobContext {
         JobContext {
             input,
             output,
             env,
             printer,
+            job_id,
         }
     }
 
@@ -212,11 +220,12 @@ impl JobContext {
             output,
             env: self.env.clone(),
             printer: self.printer.clone(),
+            job_id: self.job_id,
         }
     }
 
     pub fn compile_context(&self) -> CompileContext {
-        CompileContext::new(self.env.clone(), self.printer.clone())
+        CompileContext::new(self.env.clone(), self.printer.clone(), self.job_id)
     }
 
     pub fn execution_context(
@@ -231,6 +240,7 @@ impl JobContext {
             output: self.output.clone(),
             printer: self.printer.clone(),
             env: self.env.clone(),
+            job_id: self.job_id,
         }
     }
 }
@@ -243,6 +253,7 @@ pub struct ExecutionContext {
     pub env: Scope,
     pub this: Option<Value>,
     pub printer: Printer,
+    pub job_id: JobId,
 }
 
 pub struct StreamExecutionContext<C> {
@@ -261,7 +272,7 @@ pub struct ValueExecutionContext<C> {
 
 impl ExecutionContext {
     pub fn compile_context(&self) -> CompileContext {
-        CompileContext::new(self.env.clone(), self.printer.clone())
+        CompileContext::new(self.env.clone(), self.printer.clone(), self.job_id)
     }
 
     pub fn with_args(
@@ -276,6 +287,7 @@ impl ExecutionContext {
             printer: self.printer,
             arguments,
             this,
+            job_id: self.job_id,
         }
     }
 
@@ -290,6 +302,7 @@ impl ExecutionContext {
             printer: self.printer,
             arguments: self.arguments,
             this: self.this,
+            job_id: self.job_id,
         }
     }
 }
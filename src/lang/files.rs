@@ -31,16 +31,31 @@ impl Files {
         self.files
     }
 
+    /**
+    Read the files, or the input if no files were specified. Files with the
+    extension of a known compression format are decompressed.
+    */
     pub fn reader(self, input: ValueReceiver) -> CrushResult<Box<dyn BinaryReader + Send + Sync>> {
+        self.open(input, true)
+    }
+
+    /**
+    Read the files, or the input if no files were specified, without
+    decompressing anything.
+    */
+    pub fn raw_reader(self, input: ValueReceiver) -> CrushResult<Box<dyn BinaryReader + Send + Sync>> {
+        self.open(input, false)
+    }
+
+    fn open(self, input: ValueReceiver, decompress: bool) -> CrushResult<Box<dyn BinaryReader + Send + Sync>> {
         if !self.had_entries {
-println!("TRAFDFKDSAÖLFADSK");
             match input.recv()? {
                 Value::BinaryStream(b) => Ok(b),
                 Value::Binary(b) => Ok(BinaryReader::vec(&b)),
                 _ => argument_error("Expected either a file to read or binary pipe io"),
             }
         } else {
-            BinaryReader::paths(self.files)
+            BinaryReader::paths(self.files, decompress)
         }
     }
 
@@ -1,27 +1,44 @@
 use crate::lang::stream::{channels};
 use crate::lang::{command_invocation::CommandInvocation};
-use crate::lang::errors::{ CrushResult};
+use crate::lang::errors::{CrushResult, Kind, error};
 use std::thread::JoinHandle;
 use crate::lang::execution_context::{JobContext, CompileContext};
 use crate::lang::printer::Printer;
 
 pub enum JobJoinHandle {
     Many(Vec<JobJoinHandle>),
-    Async(JoinHandle<()>),
+    Async(JoinHandle<CrushResult<()>>),
+    Done(CrushResult<()>),
 }
 
 impl JobJoinHandle {
-    pub fn join(self, printer: &Printer) {
-        match self {
+    /**
+    Wait for every command of the job to finish. The first error is returned,
+    any further errors are printed. Errors caused by a later command in the
+    pipeline no longer reading its input are not errors at all.
+    */
+    pub fn join(self, printer: &Printer) -> CrushResult<()> {
+        let res = match self {
             JobJoinHandle::Async(a) => match a.join() {
-                Ok(_) => {},
-                Err(_) => printer.error("Unknown error while waiting for command to exit"),
+                Ok(res) => res,
+                Err(_) => error("Unknown error while waiting for command to exit"),
             },
+            JobJoinHandle::Done(res) => res,
             JobJoinHandle::Many(v) => {
+                let mut res = Ok(());
                 for j in v {
-                    j.join(printer);
+                    match (&res, j.join(printer)) {
+                        (Ok(_), Err(e)) => res = Err(e),
+                        (Err(_), r) => printer.handle_error(r),
+                        _ => {}
+                    }
                 }
+                res
             }
+        };
+        match res {
+            Err(e) if e.kind == Kind::SendError => Ok(()),
+            r => r,
         }
     }
 }
@@ -29,11 +46,16 @@ impl JobJoinHandle {
 #[derive(Clone)]
 pub struct Job {
     commands: Vec<CommandInvocation>,
+    background: bool,
 }
 
 impl Job {
-    pub fn new(commands: Vec<CommandInvocation>) -> Job {
-        Job { commands }
+    pub fn new(commands: Vec<CommandInvocation>, background: bool) -> Job {
+        Job { commands, background }
+    }
+
+    pub fn is_background(&self) -> bool {
+        self.background
     }
 
     pub fn can_block(&self, context: &mut CompileContext) -> bool {
@@ -76,6 +98,6 @@ impl Job {
 
 impl ToString for Job {
     fn to_string(&self) -> String {
-        self.commands.iter().map(|c| c.to_string()).collect::<Vec<String>>().join("|")
+        self.commands.iter().map(|c| c.to_string()).collect::<Vec<String>>().join(" | ")
     }
 }
@@ -0,0 +1,388 @@
+use crate::lang::job::JobJoinHandle;
+use crate::lang::printer::Printer;
+use crate::lang::errors::{CrushResult, argument_error, to_crush_error, mandate};
+use crate::util::thread::build;
+use crossbeam::{unbounded, Sender, Receiver};
+use lazy_static::lazy_static;
+use nix::errno::Errno;
+use nix::sys::signal::{self, Signal, SigHandler, SigAction, SaFlags, SigSet};
+use nix::unistd::{Pid, pipe, read, tcsetpgrp, getpgrp};
+use std::collections::BTreeMap;
+use std::os::unix::io::RawFd;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::fmt::{self, Display, Formatter};
+
+pub type JobId = usize;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum JobState {
+    Foreground,
+    Background,
+    Stopped,
+}
+
+impl Display for JobState {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match self {
+            JobState::Foreground | JobState::Background => "Running",
+            JobState::Stopped => "Stopped",
+        })
+    }
+}
+
+pub struct JobDescription {
+    pub id: JobId,
+    pub state: JobState,
+    pub description: String,
+}
+
+/**
+Something that happened to a job that the shell waits for while the job is in
+the foreground.
+*/
+enum JobEvent {
+    Done(CrushResult<()>),
+    Stopped,
+}
+
+struct JobEntry {
+    description: String,
+    state: JobState,
+    processes: Vec<Pid>,
+    /** The process group of the external processes of a job started in the background. */
+    process_group: Option<Pid>,
+    events: (Sender<JobEvent>, Receiver<JobEvent>),
+}
+
+/**
+The job table keeps track of every job started from the top level of the shell
+as well as every job started in the background using a trailing `&`.
+
+Every job has a join handle that is waited on by a separate thread, so that the
+foreground job can be detached from the terminal when the user presses Ctrl-Z.
+The shell waits for a foreground job by blocking until the job is either done
+or stopped.
+Builtin commands are threads and can not be stopped, so a suspended job that
+has no external processes simply keeps running in the background.
+*/
+struct JobTable {
+    jobs: BTreeMap<JobId, JobEntry>,
+    foreground: Vec<JobId>,
+}
+
+lazy_static! {
+    static ref JOBS: Mutex<JobTable> = Mutex::new(JobTable {
+        jobs: BTreeMap::new(),
+        foreground: Vec::new(),
+    });
+}
+
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+/**
+The write end of a pipe that the SIGTSTP handler writes to. Writing to a pipe is
+one of the few things that can safely be done from a signal handler.
+*/
+static SUSPEND_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn handle_suspend(_: nix::libc::c_int) {
+    let fd = SUSPEND_PIPE.load(Ordering::SeqCst);
+    if fd >= 0 {
+        unsafe {
+            nix::libc::write(fd, [0u8].as_ptr() as *const nix::libc::c_void, 1);
+        }
+    }
+}
+
+fn forward_suspend_requests(pipe: RawFd) {
+    let mut buff = [0u8; 1];
+    loop {
+        match read(pipe, &mut buff) {
+            Ok(0) => break,
+            Ok(_) => request_suspend(),
+            Err(nix::Error::Sys(Errno::EINTR)) => {}
+            Err(_) => break,
+        }
+    }
+}
+
+/**
+Make the shell catch SIGTSTP instead of stopping, and let it take back the
+terminal from jobs that have been moved into the foreground. Only used in
+interactive mode.
+*/
+pub fn init() -> CrushResult<()> {
+    let (read_end, write_end) = to_crush_error(pipe())?;
+    SUSPEND_PIPE.store(write_end, Ordering::SeqCst);
+    to_crush_error(build("job:suspend").spawn(move || forward_suspend_requests(read_end)))?;
+
+    let action = SigAction::new(
+        SigHandler::Handler(handle_suspend),
+        SaFlags::SA_RESTART,
+        SigSet::empty());
+    let ignore = SigAction::new(SigHandler::SigIgn, SaFlags::empty(), SigSet::empty());
+    unsafe {
+        to_crush_error(signal::sigaction(Signal::SIGTSTP, &action))?;
+        to_crush_error(signal::sigaction(Signal::SIGTTOU, &ignore))?;
+    }
+    INTERACTIVE.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/**
+Suspend the innermost foreground job, like pressing Ctrl-Z does.
+*/
+fn request_suspend() {
+    let table = JOBS.lock().unwrap();
+    if let Some(job) = table.foreground.last().and_then(|id| table.jobs.get(id)) {
+        let _ = job.events.0.send(JobEvent::Stopped);
+    }
+}
+
+/**
+Notify the job table that an external process of the specified job has been
+stopped, e.g. because the user pressed Ctrl-Z while it had the terminal. If
+the job is in the foreground, it is suspended.
+*/
+pub fn stopped(id: JobId) {
+    if let Some(job) = JOBS.lock().unwrap().jobs.get_mut(&id) {
+        if job.state == JobState::Background {
+            job.state = JobState::Stopped;
+        }
+        let _ = job.events.0.send(JobEvent::Stopped);
+    }
+}
+
+/**
+Create a new job table entry and return its id. Ids are small integers and are
+reused once a job has finished.
+*/
+pub fn create(description: String, background: bool) -> JobId {
+    let mut table = JOBS.lock().unwrap();
+    let id = (1..).find(|id| !table.jobs.contains_key(id)).unwrap();
+    if !background {
+        table.foreground.push(id);
+    }
+    table.jobs.insert(id, JobEntry {
+        description,
+        state: if background { JobState::Background } else { JobState::Foreground },
+        processes: Vec::new(),
+        process_group: None,
+        events: unbounded(),
+    });
+    id
+}
+
+/**
+Remove a job that failed to start.
+*/
+pub fn remove(id: JobId) {
+    let mut table = JOBS.lock().unwrap();
+    table.jobs.remove(&id);
+    table.foreground.retain(|j| *j != id);
+}
+
+pub fn is_background(id: JobId) -> bool {
+    JOBS.lock().unwrap().jobs.get(&id)
+        .map(|j| j.state != JobState::Foreground)
+        .unwrap_or(false)
+}
+
+/**
+The process group that external processes of the specified job should join,
+if the job has one.
+*/
+pub fn process_group(id: JobId) -> Option<Pid> {
+    JOBS.lock().unwrap().jobs.get(&id).and_then(|j| j.process_group)
+}
+
+/**
+Register an external process as belonging to the specified job, so that it can
+be stopped and resumed together with the rest of the job. If the process was
+put in a process group of its own, that group becomes the process group of the
+job, unless the job already has one.
+*/
+pub fn add_process(id: JobId, pid: Pid, own_group: bool) {
+    if let Some(job) = JOBS.lock().unwrap().jobs.get_mut(&id) {
+        job.processes.push(pid);
+        if own_group && job.process_group.is_none() {
+            job.process_group = Some(pid);
+        }
+    }
+}
+
+pub fn remove_process(id: JobId, pid: Pid) {
+    if let Some(job) = JOBS.lock().unwrap().jobs.get_mut(&id) {
+        job.processes.retain(|p| *p != pid);
+    }
+}
+
+pub fn list() -> Vec<JobDescription> {
+    JOBS.lock().unwrap().jobs.iter()
+        .filter(|(_, job)| job.state != JobState::Foreground)
+        .map(|(id, job)| JobDescription {
+            id: *id,
+            state: job.state,
+            description: job.description.clone(),
+        })
+        .collect()
+}
+
+/**
+The job that fg and bg operate on when no job id is given, i.e. the most
+recently started job that is not in the foreground.
+*/
+pub fn current() -> CrushResult<JobId> {
+    mandate(
+        list().last().map(|j| j.id),
+        "No current job")
+}
+
+fn start(id: JobId, handle: JobJoinHandle, printer: &Printer) -> CrushResult<()> {
+    let printer = printer.clone();
+    to_crush_error(build(&format!("job:{}", id)).spawn(move || {
+        let res = handle.join(&printer);
+        if let Some(job) = JOBS.lock().unwrap().jobs.remove(&id) {
+            if job.state == JobState::Foreground {
+                let _ = job.events.0.send(JobEvent::Done(res));
+            } else {
+                printer.handle_error(res);
+                printer.line(&format!("[{}] Done    {}", id, job.description));
+            }
+        }
+    }))?;
+    Ok(())
+}
+
+/**
+Let the specified job run in the background.
+*/
+pub fn detach(id: JobId, handle: JobJoinHandle, printer: &Printer) -> CrushResult<()> {
+    let description = mandate(
+        JOBS.lock().unwrap().jobs.get(&id).map(|j| j.description.clone()),
+        "Unknown job")?;
+    printer.line(&format!("[{}] {}", id, description));
+    start(id, handle, printer)
+}
+
+/**
+Wait for the specified job to finish, or for the user to suspend it. Returns
+the error of the job, if any. A suspended job reports its errors once it is
+done.
+*/
+pub fn wait(id: JobId, handle: JobJoinHandle, printer: &Printer) -> CrushResult<()> {
+    // The job may be done and removed from the table before we get to wait
+    // for it, so the events are fetched before the job is started
+    let events = events(id)?;
+    start(id, handle, printer)?;
+    wait_foreground(id, events, printer)
+}
+
+/**
+Move a job in the background into the foreground, resume any stopped processes
+in it and wait for it to finish or be suspended again. If the processes of the
+job have a process group of their own, the terminal is handed to that group
+while the job is in the foreground.
+*/
+pub fn foreground(id: JobId, printer: &Printer) -> CrushResult<()> {
+    let (group, events) = set_state(id, JobState::Foreground)?;
+    JOBS.lock().unwrap().foreground.push(id);
+    let terminal = group.map(give_terminal).unwrap_or(false);
+    resume(id);
+    let res = wait_foreground(id, events, printer);
+    if terminal {
+        take_terminal();
+    }
+    res
+}
+
+/**
+Resume a stopped job in the background.
+*/
+pub fn background(id: JobId) -> CrushResult<()> {
+    set_state(id, JobState::Background)?;
+    resume(id);
+    Ok(())
+}
+
+/**
+Change the state of a job that is not in the foreground and return its process
+group and events. Notifications that the job was stopped that arrived while it
+was not in the foreground are discarded. Such a job never reports being done
+through its events, so they contain nothing else.
+*/
+fn set_state(id: JobId, state: JobState) -> CrushResult<(Option<Pid>, Receiver<JobEvent>)> {
+    let mut table = JOBS.lock().unwrap();
+    match table.jobs.get_mut(&id) {
+        None => argument_error(&format!("Unknown job {}", id)),
+        Some(job) => {
+            if job.state == JobState::Foreground {
+                return argument_error(&format!("Job {} is already in the foreground", id));
+            }
+            while job.events.1.try_recv().is_ok() {}
+            job.state = state;
+            Ok((job.process_group, job.events.1.clone()))
+        }
+    }
+}
+
+fn resume(id: JobId) {
+    if let Some(job) = JOBS.lock().unwrap().jobs.get(&id) {
+        for pid in &job.processes {
+            let _ = signal::kill(*pid, Signal::SIGCONT);
+        }
+    }
+}
+
+fn give_terminal(group: Pid) -> bool {
+    INTERACTIVE.load(Ordering::SeqCst) && tcsetpgrp(0, group).is_ok()
+}
+
+fn take_terminal() {
+    let _ = tcsetpgrp(0, getpgrp());
+}
+
+fn events(id: JobId) -> CrushResult<Receiver<JobEvent>> {
+    mandate(
+        JOBS.lock().unwrap().jobs.get(&id).map(|j| j.events.1.clone()),
+        "Unknown job")
+}
+
+fn wait_foreground(id: JobId, events: Receiver<JobEvent>, printer: &Printer) -> CrushResult<()> {
+    let mut res = Ok(());
+    loop {
+        match events.recv() {
+            Ok(JobEvent::Done(r)) => {
+                res = r;
+                break;
+            }
+            Ok(JobEvent::Stopped) =>
+                if suspend(id, printer) {
+                    return Ok(());
+                },
+            Err(_) => break,
+        }
+    }
+    JOBS.lock().unwrap().foreground.retain(|j| *j != id);
+    res
+}
+
+/**
+If the specified job is the innermost foreground job, stop it and return true.
+*/
+fn suspend(id: JobId, printer: &Printer) -> bool {
+    let mut table = JOBS.lock().unwrap();
+    if table.foreground.last() != Some(&id) || !table.jobs.contains_key(&id) {
+        return false;
+    }
+    table.foreground.pop();
+    if let Some(job) = table.jobs.get_mut(&id) {
+        for pid in &job.processes {
+            let _ = signal::kill(*pid, Signal::SIGTSTP);
+        }
+        job.state = if job.processes.is_empty() { JobState::Background } else { JobState::Stopped };
+        printer.line(&format!("[{}] {}    {}", id, job.state, job.description));
+    }
+    true
+}
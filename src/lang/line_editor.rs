@@ -0,0 +1,104 @@
+use crate::lang::completion::complete;
+use crate::lang::parser::{tokenize, TokenType};
+use crate::lang::scope::Scope;
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::{Context, Helper};
+use std::borrow::Cow;
+use termion::color;
+
+/**
+Hooks Crush into the line editor, so that completions are based on what is in
+scope at the prompt and the input is syntax highlighted.
+*/
+pub struct CrushHelper {
+    scope: Scope,
+}
+
+impl CrushHelper {
+    pub fn new(scope: &Scope) -> CrushHelper {
+        CrushHelper { scope: scope.clone() }
+    }
+}
+
+impl Completer for CrushHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let cwd = std::env::current_dir()?;
+        match complete(line, pos, &self.scope, &cwd) {
+            Ok((start, completions)) => Ok((
+                start,
+                completions.into_iter()
+                    .map(|c| Pair { display: c.display, replacement: c.replacement })
+                    .collect())),
+            Err(_) => Ok((pos, vec![])),
+        }
+    }
+}
+
+impl Hinter for CrushHelper {}
+
+fn token_color(token_type: TokenType) -> Option<String> {
+    match token_type {
+        TokenType::Label => Some(color::Fg(color::LightBlue).to_string()),
+        TokenType::String => Some(color::Fg(color::Green).to_string()),
+        TokenType::Field => Some(color::Fg(color::Yellow).to_string()),
+        TokenType::Regex => Some(color::Fg(color::Magenta).to_string()),
+        TokenType::Number | TokenType::Flag => Some(color::Fg(color::Cyan).to_string()),
+        TokenType::Operator => Some(color::Fg(color::Red).to_string()),
+        TokenType::Separator | TokenType::Punctuation => None,
+    }
+}
+
+fn push_colored(res: &mut String, text: &str, color: &str) {
+    res.push_str(color);
+    res.push_str(text);
+    res.push_str(&color::Fg(color::Reset).to_string());
+}
+
+/**
+Append text that isn't part of a token, i.e. whitespace and comments.
+*/
+fn push_comments(res: &mut String, mut text: &str) {
+    while let Some(start) = text.find('#') {
+        res.push_str(&text[..start]);
+        let end = text[start..].find('\n').map(|i| i + start).unwrap_or(text.len());
+        push_colored(res, &text[start..end], &color::Fg(color::LightBlack).to_string());
+        text = &text[end..];
+    }
+    res.push_str(text);
+}
+
+impl Highlighter for CrushHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        let mut res = String::new();
+        let mut pos = 0;
+        for token in tokenize(line) {
+            push_comments(&mut res, &line[pos..token.start]);
+            let text = &line[token.start..token.end];
+            match token_color(token.token_type) {
+                Some(color) => push_colored(&mut res, text, &color),
+                None => push_comments(&mut res, text),
+            }
+            pos = token.end;
+        }
+        let rest = &line[pos..];
+        let string = rest.trim_start();
+        if string.starts_with('"') {
+            // An unterminated string
+            res.push_str(&rest[..rest.len() - string.len()]);
+            push_colored(&mut res, string, &color::Fg(color::Green).to_string());
+        } else {
+            push_comments(&mut res, rest);
+        }
+        Cow::Owned(res)
+    }
+
+    fn highlight_char(&self, line: &str, _pos: usize) -> bool {
+        !line.is_empty()
+    }
+}
+
+impl Helper for CrushHelper {}
@@ -7,6 +7,7 @@ pub mod dict;
 pub mod binary;
 pub mod command;
 pub mod job;
+pub mod job_table;
 pub mod parser;
 pub mod r#struct;
 pub mod scope;
@@ -14,6 +15,7 @@ pub mod stream;
 pub mod printer;
 pub mod pretty_printer;
 pub mod errors;
+pub mod source;
 pub mod ast;
 pub mod help;
 pub mod execution_context;
@@ -21,3 +23,5 @@ pub mod serialization;
 pub mod execute;
 pub mod ordered_string_map;
 pub mod files;
+pub mod completion;
+pub mod line_editor;
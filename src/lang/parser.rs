@@ -1,9 +1,90 @@
-use crate::lang::errors::{CrushResult, to_crush_error};
+use crate::lang::errors::{CrushResult, CrushError, Kind};
 use crate::lang::job::Job;
 use crate::lang::scope::Scope;
+use crate::lang::source::{Source, Location};
+use lalrpop_util::ParseError;
+use std::sync::Arc;
 
 lalrpop_mod!(pub lalrparser, "/lang/lalrparser.rs");
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenType {
+    Label,
+    String,
+    Field,
+    Regex,
+    Number,
+    Flag,
+    Operator,
+    Separator,
+    Punctuation,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Token {
+    pub token_type: TokenType,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn token_type(text: &str) -> TokenType {
+    match text {
+        "and" | "or" | "typeof" | "neg" | "not" | "//" => TokenType::Operator,
+        "(" | ")" | "{" | "}" | "[" | "]" => TokenType::Punctuation,
+        _ => match text.chars().next() {
+            Some('"') => TokenType::String,
+            Some('^') => TokenType::Field,
+            Some(';') | Some('\n') => TokenType::Separator,
+            Some('0'..='9') => TokenType::Number,
+            Some('-') if text.len() > 2 => TokenType::Flag,
+            _ if text.starts_with("re\"") => TokenType::Regex,
+            _ if text.starts_with("r\"") => TokenType::String,
+            Some(c) if c == '\'' || c == '.' || c == '_' || c == '%' || c == '?' || c == '/' || c.is_alphabetic() =>
+                TokenType::Label,
+            _ => TokenType::Operator,
+        }
+    }
+}
+
+/**
+Split source code into tokens, leaving out whitespace and comments. Stops at the
+first piece of text that isn't a valid token, e.g. an unterminated quote.
+*/
+pub fn tokenize(text: &str) -> Vec<Token> {
+    match lalrparser::TokenListParser::new().parse(&Source::string(text), 0, text) {
+        Ok(tokens) => tokens.into_iter()
+            .map(|(start, token, end)| Token { token_type: token_type(token), start, end })
+            .collect(),
+        Err(ParseError::InvalidToken { location }) if location > 0 => tokenize(&text[..location]),
+        Err(_) => vec![],
+    }
+}
+
+/**
+Returns false if the text is the start of a valid program that needs more input
+to be complete, e.g. because a closure, a subshell or a quote hasn't been
+closed, the last command ends in a pipe or the last line ends in a backslash.
+*/
+pub fn is_complete(text: &str) -> bool {
+    match lalrparser::JobListParser::new().parse(&Source::string(text), 0, text) {
+        Err(ParseError::UnrecognizedEOF { .. }) => false,
+        // Multi-line strings that haven't been closed yet are lexed as a token
+        // of their own, which the grammar doesn't accept anywhere
+        Err(ParseError::UnrecognizedToken { token: (_, token, _), .. }) =>
+            !is_unterminated_string(token.1),
+        Err(ParseError::InvalidToken { location }) => {
+            let rest = &text[location..];
+            !(rest.starts_with('"') || rest.starts_with("r\"") || rest.starts_with('\'') || rest == "\\")
+        }
+        _ => true,
+    }
+}
+
+fn is_unterminated_string(token: &str) -> bool {
+    let quoted = token.strip_prefix('r').unwrap_or(token);
+    quoted.starts_with("\"\"\"") && (quoted.len() < 6 || !quoted.ends_with("\"\"\""))
+}
+
 pub fn parse_name(s: &str) -> Option<Vec<String>> {
     let res = s.split('/').collect::<Vec<&str>>();
     for i in res.iter() {
@@ -14,6 +95,77 @@ pub fn parse_name(s: &str) -> Option<Vec<String>> {
     Some(res.iter().map(|e| e.to_string()).collect())
 }
 
-pub fn parse(s: &str, env: &Scope) -> CrushResult<Vec<Job>> {
-    to_crush_error(lalrparser::JobListParser::new().parse(s))?.generate(env)
+fn parse_error<T: ToString, E: ToString>(err: ParseError<usize, T, E>, source: &Arc<Source>, offset: usize) -> CrushError {
+    let (start, end) = match &err {
+        ParseError::InvalidToken { location } |
+        ParseError::UnrecognizedEOF { location, .. } => (*location, *location + 1),
+        ParseError::UnrecognizedToken { token: (start, _, end), .. } |
+        ParseError::ExtraToken { token: (start, _, end) } => (*start, *end),
+        ParseError::User { .. } => (0, 0),
+    };
+    let message = match err {
+        ParseError::InvalidToken { .. } => "Invalid token".to_string(),
+        ParseError::UnrecognizedEOF { .. } => "Unexpected end of input".to_string(),
+        ParseError::UnrecognizedToken { token: (_, token, _), .. } =>
+            format!("Unexpected token {}", token.to_string()),
+        ParseError::ExtraToken { token: (_, token, _) } =>
+            format!("Extra token {}", token.to_string()),
+        ParseError::User { error } => error.to_string(),
+    };
+    CrushError::new(Kind::ParseError, &message)
+        .with_location(Location::new(source, start + offset, end + offset))
+}
+
+pub fn parse(source: &Arc<Source>, env: &Scope) -> CrushResult<Vec<Job>> {
+    parse_at(source.text(), source, 0, env)
+}
+
+/**
+Parse text that is part of a larger source, starting at the specified byte
+offset of it, so that errors point at the text in the source.
+*/
+pub fn parse_at(text: &str, source: &Arc<Source>, offset: usize, env: &Scope) -> CrushResult<Vec<Job>> {
+    lalrparser::JobListParser::new()
+        .parse(source, offset, text)
+        .map_err(|e| parse_error(e, source, offset))?
+        .generate(env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incomplete_input() {
+        assert!(!is_complete("for i=(seq 3) {"));
+        assert!(!is_complete("echo (ls"));
+        assert!(!is_complete("ls |"));
+        assert!(!is_complete("echo \"abc"));
+        assert!(!is_complete("echo \"\"\"\nabc\""));
+        assert!(!is_complete("echo r\"abc"));
+        assert!(!is_complete("echo 1 \\"));
+        assert!(is_complete("for i=(seq 3) {\necho $i\n}"));
+        assert!(is_complete("echo }"));
+        assert!(is_complete(""));
+    }
+
+    #[test]
+    fn token_types() {
+        let types = tokenize("ls | where {^name =~ re\"a.*\"} # comment")
+            .iter().map(|t| t.token_type).collect::<Vec<TokenType>>();
+        assert_eq!(types, vec![
+            TokenType::Label, TokenType::Operator, TokenType::Label, TokenType::Punctuation,
+            TokenType::Field, TokenType::Operator, TokenType::Regex, TokenType::Punctuation]);
+        let types = tokenize("echo r\"a\\b\" \"\"\"\n  x \"y\"\n\"\"\"")
+            .iter().map(|t| t.token_type).collect::<Vec<TokenType>>();
+        assert_eq!(types, vec![TokenType::Label, TokenType::String, TokenType::String]);
+    }
+
+    #[test]
+    fn interpolation_error_location() {
+        let source = Source::string("echo 1\necho \"\"\"\n  a\n  ${(}\n  \"\"\"");
+        let location = parse(&source, &Scope::create_root()).err().unwrap().location.unwrap();
+        assert_eq!(location.line(), 4);
+        assert_eq!(location.column(), 6);
+    }
 }
@@ -25,7 +25,7 @@ pub fn create_pretty_printer(printer: Printer) -> ValueSender {
                 pp.print_value(val);
             }
         })));
-    o
+    o.terminal()
 }
 
 pub struct PrettyPrinter {
@@ -79,7 +79,7 @@ pub fn format_buffer(buff: &[u8], complete: bool) -> String {
 fn is_text(buff: &[u8]) -> bool {
     let mut c = 0;
     for v in buff {
-        if is_printable(*v) {
+        if is_printable(*v) || v.is_ascii_whitespace() {
             c += 1;
         }
     }
@@ -245,9 +245,17 @@ impl PrettyPrinter {
             match reader.read(&mut buff[used..buff_len]) {
                 Ok(len) => {
                     if len == 0 {
+                        if used == 0 {
+                            return;
+                        }
                         complete = true;
                         break;
                     }
+                    if used == 0 && is_text(&buff[0..len]) {
+                        buff.truncate(len);
+                        self.print_text(&mut reader, buff);
+                        return;
+                    }
                     used += len;
                     if used == buff.len() {
                         break;
@@ -262,6 +270,31 @@ impl PrettyPrinter {
         self.printer.line(format_buffer(&buff[0..used], complete).as_str());
     }
 
+    /**
+    Print text line by line as it arrives, so that the output of long running
+    commands can be followed.
+    */
+    fn print_text(&self, reader: &mut impl Read, mut pending: Vec<u8>) {
+        let mut buff = vec![0; 4096];
+        loop {
+            while let Some(idx) = pending.iter().position(|c| *c == b'\n') {
+                self.printer.line(&String::from_utf8_lossy(&pending[0..idx]));
+                pending.drain(0..=idx);
+            }
+            match reader.read(&mut buff) {
+                Ok(0) => break,
+                Ok(len) => pending.extend_from_slice(&buff[0..len]),
+                Err(e) => {
+                    self.printer.error(e.to_string().as_str());
+                    return;
+                }
+            }
+        }
+        if !pending.is_empty() {
+            self.printer.line(&String::from_utf8_lossy(&pending));
+        }
+    }
+
     fn print_partial(&self, data: Vec<Row>, types: &[ColumnType], indent: usize, has_table: bool) {
         if data.len() == 0 {
             return;
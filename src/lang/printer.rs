@@ -28,7 +28,7 @@ pub fn init() -> (Printer, JoinHandle<()>) {
             while let Ok(message) = receiver.recv() {
                 match message {
                     Error(err) => eprintln!("Error: {}", err),
-                    CrushError(err) => eprintln!("Error: {}", err.message),
+                    CrushError(err) => eprint!("{}", render_error(&err)),
                     Line(line) => println!("{}", line),
 //                        Lines(lines) => for line in lines {println!("{}", line)},
                 }
@@ -37,6 +37,43 @@ pub fn init() -> (Printer, JoinHandle<()>) {
     )
 }
 
+/**
+Render an error the way it is shown to the user. If the location of the error
+is known, the offending line of source code is shown with the failing part
+underlined.
+*/
+fn render_error(err: &CrushError) -> String {
+    let mut res = format!("Error: {}\n", err.message);
+    match (&err.location, &err.command) {
+        (Some(location), command) => {
+            let line = location.line().to_string();
+            let margin = " ".repeat(line.len());
+            let text = location.line_text();
+            let indent = text.chars()
+                .take(location.column() - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect::<String>();
+            res.push_str(&format!(
+                "{}--> {}:{}:{}",
+                margin, location.source().name().unwrap_or("<input>"), line, location.column()));
+            if let Some(command) = command {
+                res.push_str(&format!(" in {}", command));
+            }
+            res.push_str(&format!(
+                "\n{} |\n{} | {}\n{} | {}{}\n",
+                margin, line, text, margin, indent, "^".repeat(location.width().max(1))));
+        }
+        (None, Some(command)) => res.push_str(&format!("  in {}\n", command)),
+        (None, None) => {}
+    }
+    let mut cause = &err.cause;
+    while let Some(c) = cause {
+        res.push_str(&format!("Caused by: {}\n", c.message));
+        cause = &c.cause;
+    }
+    res
+}
+
 impl Printer {
     pub fn line(&self, line: &str) {
         self.handle_error(to_crush_error(self.sender.send(PrinterMessage::Line(line.to_string()))));
@@ -1,9 +1,10 @@
-use crate::lang::errors::{error, CrushResult, mandate};
+use crate::lang::errors::{error, CrushError, CrushResult, mandate};
 use std::sync::{Arc, Mutex, MutexGuard};
 use crate::lang::{value::Value, value::ValueType};
 use ordered_map::OrderedMap;
 use crate::lang::execution_context::ExecutionContext;
 use crate::lang::command::{CrushCommand, Command, OutputType};
+use crate::lang::argument::ArgumentDescription;
 use crate::lang::r#struct::Struct;
 use crate::util::identity_arc::Identity;
 use crate::lang::help::Help;
@@ -59,10 +60,25 @@ impl ScopeLoader {
         short_help: &'static str,
         long_help: Option<&'static str>,
         output: OutputType,
+    ) -> CrushResult<()> {
+        self.declare_command_with_arguments(
+            name, call, can_block, signature, short_help, long_help, output, vec![])
+    }
+
+    pub fn declare_command_with_arguments(
+        &mut self,
+        name: &str,
+        call: fn(ExecutionContext) -> CrushResult<()>,
+        can_block: bool,
+        signature: &'static str,
+        short_help: &'static str,
+        long_help: Option<&'static str>,
+        output: OutputType,
+        arguments: Vec<ArgumentDescription>,
     ) -> CrushResult<()> {
         let mut full_name = self.path.clone();
         full_name.push(name.to_string());
-        let command = CrushCommand::command(call, can_block, full_name, signature, short_help, long_help, output);
+        let command = CrushCommand::command(call, can_block, full_name, signature, short_help, long_help, output, arguments);
         if self.mapping.contains_key(name) {
             return error(format!("Variable ${{{}}} already exists", name).as_str());
         }
@@ -104,6 +120,17 @@ impl ScopeLoader {
     }
 }
 
+/**
+How far a lazy namespace has come in loading its members.
+*/
+enum LoadState {
+    Unloaded,
+    Loading,
+    Loaded,
+    /** Loading failed, so every access to the namespace fails with the same error. */
+    Failed(CrushError),
+}
+
 pub struct ScopeData {
     /** This is the parent scope used to perform variable name resolution. If a variable lookup
      fails in the current scope, it proceeds to this scope. This is usually the scope in which this
@@ -128,6 +155,10 @@ pub struct ScopeData {
     /** True if this scope is a loop. Required to implement the break/continue commands.*/
     pub is_loop: bool,
 
+    /** True if a failed job should stop any closure called from this scope, even if it is not the
+    last job of the closure. Required to implement the try command. */
+    pub stops_on_error: bool,
+
     /** True if this scope should stop execution, i.e. if the continue or break commands have been
     called.  */
     pub is_stopped: bool,
@@ -137,7 +168,7 @@ pub struct ScopeData {
     pub is_readonly: bool,
 
     pub name: Option<String>,
-    is_loaded: bool,
+    load_state: LoadState,
     loader: Option<Box<dyn Send + FnOnce(&mut ScopeLoader) -> CrushResult<()>>>,
 }
 
@@ -147,12 +178,13 @@ impl ScopeData {
             parent_scope,
             calling_scope,
             is_loop,
+            stops_on_error: false,
             uses: Vec::new(),
             mapping: OrderedMap::new(),
             is_stopped: false,
             is_readonly: false,
             name,
-            is_loaded: true,
+            load_state: LoadState::Loaded,
             loader: None,
         }
     }
@@ -162,12 +194,13 @@ impl ScopeData {
             parent_scope,
             calling_scope,
             is_loop,
+            stops_on_error: false,
             uses: Vec::new(),
             mapping: OrderedMap::new(),
             is_stopped: false,
             is_readonly: false,
             name,
-            is_loaded: false,
+            load_state: LoadState::Unloaded,
             loader: Some(loader),
         }
     }
@@ -179,12 +212,13 @@ impl Clone for ScopeData {
             parent_scope: self.parent_scope.clone(),
             calling_scope: self.calling_scope.clone(),
             is_loop: self.is_loop,
+            stops_on_error: self.stops_on_error,
             uses: self.uses.clone(),
             mapping: self.mapping.clone(),
             is_stopped: self.is_stopped,
             is_readonly: self.is_readonly,
             name: self.name.clone(),
-            is_loaded: true,
+            load_state: LoadState::Loaded,
             loader: None,
         }
     }
@@ -210,10 +244,11 @@ impl Scope {
                 uses: vec![],
                 mapping: OrderedMap::new(),
                 is_loop,
+                stops_on_error: false,
                 is_stopped,
                 is_readonly,
                 name,
-                is_loaded: true,
+                load_state: LoadState::Loaded,
                 loader: None,
             })),
         }
@@ -229,6 +264,27 @@ impl Scope {
         }
     }
 
+    /**
+    Create a scope to call the body of a try command from, so that the first job that fails in
+    the body, or in any closure called from it, stops the body.
+    */
+    pub fn create_try_child(&self) -> Scope {
+        let res = self.create_child(self, false);
+        res.data.lock().unwrap().stops_on_error = true;
+        res
+    }
+
+    pub fn stops_on_error(&self) -> bool {
+        let data = self.data.lock().unwrap();
+        if data.stops_on_error {
+            true
+        } else {
+            let caller = data.calling_scope.clone();
+            drop(data);
+            caller.map(|p| p.stops_on_error()).unwrap_or(false)
+        }
+    }
+
     pub fn create_lazy_namespace(&self, name: &str, loader: Box<dyn Send + FnOnce(&mut ScopeLoader) -> CrushResult<()>>) -> CrushResult<Scope> {
         let res = Scope {
             data: Arc::from(Mutex::new(ScopeData::lazy(None, Some(self.clone()), false, Some(name.to_string()), loader))),
@@ -287,30 +343,46 @@ impl Scope {
 
     fn lock(&self) -> CrushResult<MutexGuard<ScopeData>> {
         let mut data = self.data.lock().unwrap();
-        if data.is_loaded {
-            return Ok(data);
+        match &data.load_state {
+            LoadState::Loaded => return Ok(data),
+            LoadState::Failed(e) => return Err(e.clone()),
+            LoadState::Loading => return error(&format!(
+                "Namespace {} was accessed while it is being loaded",
+                data.name.as_deref().unwrap_or("<anonymous>"))),
+            LoadState::Unloaded => {}
         }
-
+        data.load_state = LoadState::Loading;
+        let loader = mandate(data.loader.take(), "Missing module loader")?;
+        let name = data.name.clone();
+        let parent = data.calling_scope.as_ref().unwrap().clone();
+        // Loaders may run code that looks up names in this scope from other
+        // threads, so the lock can't be held while loading.
         drop(data);
-        let path = self.full_path()?;
 
-        data = self.data.lock().unwrap();
-        if data.is_loaded {
-            return Ok(data);
-        }
-        data.is_loaded = true;
-        let loader = mandate(data.loader.take(), "Missing module loader")?;
+        // Imported modules may be declared in anonymous scopes, in which case
+        // there is no path from the root to them.
+        let path = self.path().unwrap_or_else(|| name.into_iter().collect());
         let mut tmp = ScopeLoader {
             mapping: OrderedMap::new(),
             path,
-            parent: data.calling_scope.as_ref().unwrap().clone(),
+            parent,
             scope: self.clone(),
         };
-        loader(&mut tmp)?;
-        tmp.copy_into(&mut data.mapping);
-        data.is_readonly = true;
+        let res = loader(&mut tmp);
 
-        Ok(data)
+        data = self.data.lock().unwrap();
+        match res {
+            Ok(()) => {
+                tmp.copy_into(&mut data.mapping);
+                data.is_readonly = true;
+                data.load_state = LoadState::Loaded;
+                Ok(data)
+            }
+            Err(e) => {
+                data.load_state = LoadState::Failed(e.clone());
+                Err(e)
+            }
+        }
     }
 
     pub fn clear(&self) {
@@ -320,18 +392,24 @@ impl Scope {
     }
 
     pub fn full_path(&self) -> CrushResult<Vec<String>> {
+        mandate(self.path(), "Tried to get full path of anonymous scope")
+    }
+
+    /**
+    The names of the scopes from the root to this one, or None if any of them
+    is anonymous.
+    */
+    fn path(&self) -> Option<Vec<String>> {
         let data = self.data.lock().unwrap();
-        match data.name.clone() {
-            None => error("Tried to get full path of anonymous scope"),
-            Some(name) => match data.calling_scope.clone() {
-                None => Ok(vec![name]),
-                Some(parent) => {
-                    drop(data);
-                    let mut full_path = parent.full_path()?;
-                    full_path.push(name);
-                    Ok(full_path)
-                }
-            },
+        let name = data.name.clone()?;
+        match data.calling_scope.clone() {
+            None => Some(vec![name]),
+            Some(parent) => {
+                drop(data);
+                let mut path = parent.path()?;
+                path.push(name);
+                Some(path)
+            }
         }
     }
 
@@ -521,11 +599,13 @@ impl Scope {
     }
 
     pub fn dump(&self, map: &mut OrderedMap<String, ValueType>) -> CrushResult<()> {
-        if let Some(p) = self.lock()?.parent_scope.clone() {
+        let parent = self.lock()?.parent_scope.clone();
+        if let Some(p) = parent {
             p.dump(map)?;
         }
 
-        for u in self.data.lock().unwrap().uses.clone().iter().rev() {
+        let uses = self.data.lock().unwrap().uses.clone();
+        for u in uses.iter().rev() {
             u.dump(map)?;
         }
 
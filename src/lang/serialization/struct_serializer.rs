@@ -1,4 +1,4 @@
-use crate::lang::r#struct::Struct;
+use crate::lang::r#struct::{Struct, Kind};
 use super::{Serializable, DeserializationState, SerializationState};
 use super::super::errors::{CrushResult, error};
 use super::model::{Element, element};
@@ -22,6 +22,12 @@ impl Serializable<Struct> for Struct {
                         };
 
                         res.set_parent(parent);
+                        res.set_kind(match s.kind {
+                            None | Some(model::r#struct::Kind::Plain(_)) => None,
+                            Some(model::r#struct::Kind::IsEnum(_)) => Some(Kind::Enum),
+                            Some(model::r#struct::Kind::Variant(name_id)) =>
+                                Some(Kind::Variant(String::deserialize(name_id as usize, elements, state)?)),
+                        });
 
                         for member_idx in &s.members {
                             match elements[*member_idx as usize].element.as_ref().unwrap() {
@@ -62,10 +68,17 @@ impl Serializable<Struct> for Struct {
                     elements.push(el);
                 }
 
+                let kind = match self.kind() {
+                    None => model::r#struct::Kind::Plain(true),
+                    Some(Kind::Enum) => model::r#struct::Kind::IsEnum(true),
+                    Some(Kind::Variant(name)) => model::r#struct::Kind::Variant(name.serialize(elements, state)? as u64),
+                };
+
                 elements[idx] = model::Element {
                     element: Some(element::Element::Struct(model::Struct {
                         parent: None,
                         members,
+                        kind: Some(kind),
                     })),
                 };
 
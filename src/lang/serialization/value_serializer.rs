@@ -33,6 +33,7 @@ fn serialize_simple(value: &Value, elements: &mut Vec<Element>, state: &mut Seri
             Value::Empty() => element::Element::Empty(false),
             Value::Time(d) => element::Element::Time(d.timestamp_nanos()),
             Value::Field(f) => element::Element::Field(model::Strings { elements: f.clone() }),
+            Value::BigInteger(i) => element::Element::LargeInteger(i.to_string()),
             _ => return error("Expected simple value"),
         }),
     });
@@ -55,8 +56,8 @@ impl Serializable<Value> for Value {
             element::Element::Bool(v) => Ok(Value::Bool(*v)),
             element::Element::Empty(_) => Ok(Value::Empty()),
 
-            element::Element::SmallInteger(_) | element::Element::LargeInteger(_) =>
-                Ok(Value::Integer(i128::deserialize(id, elements, state)?)),
+            element::Element::SmallInteger(i) => Ok(Value::Integer(*i as i128)),
+            element::Element::LargeInteger(s) => Ok(Value::big_integer(to_crush_error(s.parse())?)),
 
             element::Element::Duration(d) =>
                 Ok(Value::Duration(
@@ -91,7 +92,7 @@ impl Serializable<Value> for Value {
         match self {
             Value::String(_) | Value::Glob(_) | Value::Regex(_, _) | Value::File(_) |
             Value::Binary(_) | Value::Float(_) | Value::Bool(_) | Value::Empty() |
-            Value::Time(_) | Value::Field(_) => serialize_simple(self, elements, state),
+            Value::Time(_) | Value::Field(_) | Value::BigInteger(_) => serialize_simple(self, elements, state),
 
             Value::Integer(s) => s.serialize(elements, state),
 
@@ -0,0 +1,134 @@
+use std::fmt::{self, Debug, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+
+/**
+A piece of Crush code, along with the name of the file it was read from, if
+any. Kept around for as long as any code parsed from it exists, so that errors
+can point at the offending line.
+*/
+pub struct Source {
+    name: Option<String>,
+    text: String,
+}
+
+impl Source {
+    pub fn file(path: &Path, text: String) -> Arc<Source> {
+        Arc::new(Source {
+            name: Some(path.to_string_lossy().to_string()),
+            text,
+        })
+    }
+
+    pub fn string(text: &str) -> Arc<Source> {
+        Arc::new(Source {
+            name: None,
+            text: text.to_string(),
+        })
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/**
+A span of a source, as byte offsets.
+*/
+#[derive(Clone)]
+pub struct Location {
+    source: Arc<Source>,
+    start: usize,
+    end: usize,
+}
+
+impl Location {
+    pub fn new(source: &Arc<Source>, start: usize, end: usize) -> Location {
+        Location {
+            source: source.clone(),
+            start: start.min(source.text.len()),
+            end: end.min(source.text.len()).max(start),
+        }
+    }
+
+    pub fn source(&self) -> &Arc<Source> {
+        &self.source
+    }
+
+    /**
+    The byte offset of the start of the span.
+    */
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    fn line_start(&self) -> usize {
+        self.source.text[..self.start].rfind('\n').map(|i| i + 1).unwrap_or(0)
+    }
+
+    /**
+    The line number of the start of the span, counting from one.
+    */
+    pub fn line(&self) -> usize {
+        self.source.text[..self.start].matches('\n').count() + 1
+    }
+
+    /**
+    The column of the start of the span, counting from one.
+    */
+    pub fn column(&self) -> usize {
+        self.source.text[self.line_start()..self.start].chars().count() + 1
+    }
+
+    /**
+    The full line that the span starts on.
+    */
+    pub fn line_text(&self) -> &str {
+        let start = self.line_start();
+        let end = self.source.text[start..].find('\n').map(|i| i + start).unwrap_or(self.source.text.len());
+        &self.source.text[start..end]
+    }
+
+    /**
+    The number of characters of the span that are on its first line.
+    */
+    pub fn width(&self) -> usize {
+        let end = self.source.text[self.start..self.end].find('\n')
+            .map(|i| i + self.start)
+            .unwrap_or(self.end);
+        self.source.text[self.start..end].chars().count()
+    }
+}
+
+impl Debug for Location {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.source.name().unwrap_or("<input>"), self.line(), self.column())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_in_multi_line_source() {
+        let source = Source::string("echo 1\n  foo 2 3\necho 4");
+        let location = Location::new(&source, 9, 16);
+        assert_eq!(location.line(), 2);
+        assert_eq!(location.column(), 3);
+        assert_eq!(location.line_text(), "  foo 2 3");
+        assert_eq!(location.width(), 7);
+    }
+
+    #[test]
+    fn location_spanning_lines() {
+        let source = Source::string("foo {\n bar\n}");
+        let location = Location::new(&source, 0, 12);
+        assert_eq!(location.line(), 1);
+        assert_eq!(location.width(), 5);
+    }
+}
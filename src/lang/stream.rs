@@ -22,9 +22,22 @@ pub fn black_hole() -> ValueSender {
 #[derive(Clone)]
 pub struct ValueSender {
     sender: Sender<Value>,
+    is_terminal: bool,
 }
 
 impl ValueSender {
+    /**
+    Mark this sender as the input of the pretty printer, i.e. anything sent to
+    it ends up on the terminal.
+    */
+    pub fn terminal(self) -> ValueSender {
+        ValueSender { sender: self.sender, is_terminal: true }
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        self.is_terminal
+    }
+
     pub fn send(&self, cell: Value) -> CrushResult<()> {
         match self.sender.send(cell) {
             Ok(_) => Ok(()),
@@ -67,7 +80,7 @@ impl OutputStream {
         };
         match native_output {
             Ok(_) => Ok(()),
-            Err(_) => error("Broken pipe"),
+            Err(_) => send_error(),
         }
     }
 }
@@ -131,7 +144,7 @@ impl InputStream {
 
 pub fn channels() -> (ValueSender, ValueReceiver) {
     let (send, recv) = bounded(1);
-    (ValueSender {sender: send}, ValueReceiver { receiver: recv })
+    (ValueSender {sender: send, is_terminal: false}, ValueReceiver { receiver: recv })
 }
 
 pub fn streams(signature: Vec<ColumnType>) -> (OutputStream, InputStream) {
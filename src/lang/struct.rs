@@ -8,11 +8,24 @@ use std::cmp::Ordering;
 use crate::util::replace::Replace;
 use crate::util::identity_arc::Identity;
 
+/**
+What a struct represents, if it is more than a collection of members. This is
+kept outside of the members, so that it can't be read or overwritten like one.
+*/
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub enum Kind {
+    /** An enum, whose members construct the values of its variants */
+    Enum,
+    /** A value of the enum variant with this name, whose members are the payload */
+    Variant(String),
+}
+
 #[derive(Clone)]
 struct StructData {
     parent: Option<Struct>,
     lookup: HashMap<String, usize>,
     cells: Vec<Value>,
+    kind: Option<Kind>,
 }
 
 #[derive(Clone)]
@@ -32,6 +45,7 @@ impl Hash for Struct {
         data.cells.iter().for_each(|value| {
             value.hash(state);
         });
+        data.kind.hash(state);
         let p = data.parent.clone();
         drop(data);
         p.hash(state);
@@ -42,7 +56,7 @@ impl PartialEq for Struct {
     fn eq(&self, other: &Self) -> bool {
         let us = self.data.lock().unwrap().clone();
         let them = other.data.lock().unwrap().clone();
-        if us.cells.len() != them.cells.len() {
+        if us.cells.len() != them.cells.len() || us.kind != them.kind {
             return false;
         }
         for (v1, v2) in us.cells.iter().zip(them.cells.iter()) {
@@ -82,6 +96,7 @@ impl Struct {
                 parent,
                 cells,
                 lookup,
+                kind: None,
             }))
         }
     }
@@ -101,6 +116,7 @@ impl Struct {
                 parent: None,
                 lookup,
                 cells,
+                kind: None,
             }))
         }
     }
@@ -187,6 +203,7 @@ impl Struct {
                 parent: data.parent.clone(),
                 lookup: data.lookup.clone(),
                 cells: data.cells.iter().map(|value| value.clone().materialize()).collect(),
+                kind: data.kind.clone(),
             }))
         }
     }
@@ -194,6 +211,14 @@ impl Struct {
     pub fn set_parent(&self, parent: Option<Struct>) {
         self.data.lock().unwrap().parent = parent;
     }
+
+    pub fn kind(&self) -> Option<Kind> {
+        self.data.lock().unwrap().kind.clone()
+    }
+
+    pub fn set_kind(&self, kind: Option<Kind>) {
+        self.data.lock().unwrap().kind = kind;
+    }
 }
 
 impl ToString for Struct {
@@ -22,6 +22,9 @@ use crate::util::time::duration_format;
 use crate::lang::scope::Scope;
 use crate::lang::r#struct::Struct;
 use crate::lang::stream::{streams, InputStream, Stream};
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
+use std::convert::TryFrom;
 
 pub use value_type::ValueType;
 pub use value_definition::ValueDefinition;
@@ -37,6 +40,12 @@ pub type Field = Vec<String>;
 pub enum Value {
     String(String),
     Integer(i128),
+    /**
+    An integer that doesn't fit in an i128. Arithmetic that overflows an
+    Integer produces a BigInteger, and a BigInteger that fits in an i128 is
+    always turned back into an Integer, see Value::big_integer.
+    */
+    BigInteger(BigInt),
     Time(DateTime<Local>),
     Duration(Duration),
     Field(Field),
@@ -63,6 +72,7 @@ impl ToString for Value {
         match self {
             Value::String(val) => val.to_string(),
             Value::Integer(val) => val.to_string(),
+            Value::BigInteger(val) => val.to_string(),
             Value::Time(val) => val.format("%Y-%m-%d %H:%M:%S %z").to_string(),
             Value::Field(val) => format!(r"^{}", val.join(":")),
             Value::Glob(val) => val.to_string(),
@@ -154,7 +164,7 @@ impl Value {
 
     pub fn alignment(&self) -> Alignment {
         match self {
-            Value::Time(_) | Value::Duration(_) | Value::Integer(_) => Alignment::Right,
+            Value::Time(_) | Value::Duration(_) | Value::Integer(_) | Value::BigInteger(_) => Alignment::Right,
             _ => Alignment::Left,
         }
     }
@@ -168,6 +178,16 @@ impl Value {
         Value::String(s.to_string())
     }
 
+    /**
+    An integer value, using an i128 if the integer fits in one.
+    */
+    pub fn big_integer(i: BigInt) -> Value {
+        match i128::try_from(&i) {
+            Ok(i) => Value::Integer(i),
+            Err(_) => Value::BigInteger(i),
+        }
+    }
+
     pub fn stream(&self) -> Option<Stream> {
         match self {
             Value::TableStream(s) => Some(Box::from(s.clone())),
@@ -181,7 +201,7 @@ impl Value {
     pub fn value_type(&self) -> ValueType {
         match self {
             Value::String(_) => ValueType::String,
-            Value::Integer(_) => ValueType::Integer,
+            Value::Integer(_) | Value::BigInteger(_) => ValueType::Integer,
             Value::Time(_) => ValueType::Time,
             Value::Field(_) => ValueType::Field,
             Value::Glob(_) => ValueType::Glob,
@@ -261,8 +281,11 @@ impl Value {
         match (&self, &new_type) {
             (Value::Integer(i), ValueType::Bool) =>
                 return Ok(Value::Bool(*i != 0)),
+            (Value::BigInteger(_), ValueType::Bool) =>
+                return Ok(Value::Bool(true)),
             (Value::Float(f), ValueType::Integer) =>
-                return Ok(Value::Integer(*f as i128)),
+                return Ok(Value::big_integer(
+                    mandate(BigInt::from_f64(*f), "Can't convert a float that is not finite to an integer")?)),
             _ => {}
         }
 
@@ -271,7 +294,7 @@ impl Value {
         match new_type {
             ValueType::File => Ok(Value::File(PathBuf::from(str_val.as_str()))),
             ValueType::Glob => Ok(Value::Glob(Glob::new(str_val.as_str()))),
-            ValueType::Integer => to_crush_error(str_val.parse::<i128>()).map(Value::Integer),
+            ValueType::Integer => ValueType::Integer.parse(&str_val),
             ValueType::Field => Ok(Value::Field(vec![str_val])),
             ValueType::Regex => to_crush_error(Regex::new(str_val.as_str()).map(|v| Value::Regex(str_val, v))),
             ValueType::Binary => Ok(Value::Binary(str_val.bytes().collect())),
@@ -304,6 +327,7 @@ impl Clone for Value {
         match self {
             Value::String(v) => Value::String(v.clone()),
             Value::Integer(v) => Value::Integer(*v),
+            Value::BigInteger(v) => Value::BigInteger(v.clone()),
             Value::Time(v) => Value::Time(*v),
             Value::Field(v) => Value::Field(v.clone()),
             Value::Glob(v) => Value::Glob(v.clone()),
@@ -349,6 +373,7 @@ impl std::hash::Hash for Value {
         match self {
             Value::String(v) => v.hash(state),
             Value::Integer(v) => v.hash(state),
+            Value::BigInteger(v) => v.hash(state),
             Value::Time(v) => v.hash(state),
             Value::Field(v) => v.hash(state),
             Value::Glob(v) => v.hash(state),
@@ -386,6 +411,7 @@ impl std::cmp::PartialEq for Value {
         match (self, other) {
             (Value::String(val1), Value::String(val2)) => val1 == val2,
             (Value::Integer(val1), Value::Integer(val2)) => val1 == val2,
+            (Value::BigInteger(val1), Value::BigInteger(val2)) => val1 == val2,
             (Value::Time(val1), Value::Time(val2)) => val1 == val2,
             (Value::Duration(val1), Value::Duration(val2)) => val1 == val2,
             (Value::Field(val1), Value::Field(val2)) => val1 == val2,
@@ -423,6 +449,9 @@ impl std::cmp::PartialOrd for Value {
         match (self, other) {
             (Value::String(val1), Value::String(val2)) => Some(val1.cmp(val2)),
             (Value::Integer(val1), Value::Integer(val2)) => Some(val1.cmp(val2)),
+            (Value::BigInteger(val1), Value::BigInteger(val2)) => Some(val1.cmp(val2)),
+            (Value::Integer(val1), Value::BigInteger(val2)) => Some(BigInt::from(*val1).cmp(val2)),
+            (Value::BigInteger(val1), Value::Integer(val2)) => Some(val1.cmp(&BigInt::from(*val2))),
             (Value::Time(val1), Value::Time(val2)) => Some(val1.cmp(val2)),
             (Value::Duration(val1), Value::Duration(val2)) => Some(val1.cmp(val2)),
             (Value::Field(val1), Value::Field(val2)) => Some(val1.cmp(val2)),
@@ -483,6 +512,17 @@ mod tests {
         assert_eq!(Value::string("fad").convert(ValueType::Field).is_err(), false);
     }
 
+    #[test]
+    fn big_integers() {
+        let big = Value::string("170141183460469231731687303715884105728").convert(ValueType::Integer).unwrap();
+        assert!(matches!(big, Value::BigInteger(_)));
+        assert_eq!(big.value_type(), ValueType::Integer);
+        assert_eq!(big.to_string(), "170141183460469231731687303715884105728");
+        assert!(Value::Integer(i128::MAX) < big);
+        assert!(matches!(Value::big_integer(BigInt::from(7)), Value::Integer(7)));
+        assert!(matches!(Value::Float(1e40).convert(ValueType::Integer).unwrap(), Value::BigInteger(_)));
+    }
+
     #[test]
     fn test_duration_format() {
         assert_eq!(duration_format(&Duration::microseconds(0)), "0".to_string());
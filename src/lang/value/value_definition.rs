@@ -58,8 +58,17 @@ impl ValueDefinition {
                     return block_error();
                 }
                 let j = def.invoke(context.job_context(first_input, last_output))?;
-                context.dependencies.push(j);
-                (None, last_input.recv()?)
+                match last_input.recv() {
+                    Ok(value) => {
+                        context.dependencies.push(j);
+                        (None, value)
+                    }
+                    Err(e) => {
+                        // The job failed without producing a value, report why
+                        j.join(&context.printer)?;
+                        return Err(e);
+                    }
+                }
             }
             ValueDefinition::ClosureDefinition(name, p, c) =>
                 (None, Value::Command(CrushCommand::closure(name.clone(), p.clone(), c.clone(), &context.env))),
@@ -9,6 +9,7 @@ use crate::lib::types;
 use lazy_static::lazy_static;
 use crate::lang::help::Help;
 use std::cmp::max;
+use num_bigint::BigInt;
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
 pub enum ValueType {
@@ -120,7 +121,10 @@ impl ValueType {
             ValueType::String => Ok(Value::string(s)),
             ValueType::Integer => match s.parse::<i128>() {
                 Ok(n) => Ok(Value::Integer(n)),
-                Err(e) => error(e.to_string().as_str()),
+                Err(e) => match s.parse::<BigInt>() {
+                    Ok(n) => Ok(Value::big_integer(n)),
+                    Err(_) => error(e.to_string().as_str()),
+                },
             }
             ValueType::Field => Ok(Value::Field(mandate(parse_name(s), "Invalid field name")?)),
             ValueType::Glob => Ok(Value::Glob(Glob::new(s))),
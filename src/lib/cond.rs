@@ -23,6 +23,7 @@ pub fn and(mut context: ExecutionContext) -> CrushResult<()> {
                     env: context.env.clone(),
                     this: None,
                     printer: context.printer.clone(),
+                    job_id: context.job_id,
                 };
                 c.invoke(cc)?;
                 match receiver.recv()? {
@@ -61,6 +62,7 @@ pub fn or(mut context: ExecutionContext) -> CrushResult<()> {
                     env: context.env.clone(),
                     this: None,
                     printer: context.printer.clone(),
+                    job_id: context.job_id,
                 };
                 c.invoke(cc)?;
                 match receiver.recv()? {
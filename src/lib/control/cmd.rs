@@ -0,0 +1,276 @@
+use crate::lang::errors::{CrushResult, argument_error, error, to_crush_error};
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::scope::Scope;
+use crate::lang::value::Value;
+use crate::lang::argument::Argument;
+use crate::lang::stream::{ValueReceiver, CrushStream};
+use crate::lang::binary::BinaryReader;
+use crate::lang::printer::Printer;
+use crate::lang::r#struct::Struct;
+use crate::lang::job_table::{self, JobId};
+use crate::util::thread::build;
+use super::pty;
+use std::process::{Command, Stdio, Child, ChildStdin, ChildStderr};
+use std::os::unix::process::CommandExt;
+use std::io::{BufRead, BufReader, Write, ErrorKind};
+use std::thread::JoinHandle;
+use std::os::unix::io::IntoRawFd;
+use std::time::Instant;
+use nix::unistd::Pid;
+use nix::errno::Errno;
+use nix::sys::wait::{WaitPidFlag, WaitStatus};
+use chrono::Duration;
+
+fn build_command(mut arguments: Vec<Argument>) -> CrushResult<Command> {
+    if arguments.is_empty() {
+        return argument_error("No command given");
+    }
+    let mut cmd = match arguments.remove(0).value {
+        Value::File(f) => Command::new(f.as_os_str()),
+        Value::String(s) => Command::new(&s),
+        _ => return argument_error("Not a valid command"),
+    };
+    for a in arguments.drain(..) {
+        match a.argument_type {
+            None => {
+                cmd.arg(a.value.to_string());
+            }
+            Some(name) => {
+                if name.len() == 1 {
+                    cmd.arg(format!("-{}", name));
+                } else {
+                    cmd.arg(format!("--{}", name));
+                }
+                match a.value {
+                    Value::Bool(true) => {}
+                    _ => {
+                        cmd.arg(a.value.to_string());
+                    }
+                }
+            }
+        }
+    }
+    Ok(cmd)
+}
+
+/**
+Start the external command with its output streams connected to pipes, and
+register the process in the job table. Standard input is only connected to a
+pipe if something is piped into the command, otherwise it is inherited from
+the shell, so that interactive programs can read from the terminal.
+*/
+fn start(mut cmd: Command, input: &Value, job_id: JobId) -> CrushResult<(Child, Pid)> {
+    let background = job_table::is_background(job_id);
+    if background {
+        // Keep terminal signals like Ctrl-Z from reaching background jobs. All
+        // processes of a job share a process group, so that the terminal can
+        // be handed to them if the job is moved into the foreground.
+        let group = job_table::process_group(job_id).unwrap_or_else(|| Pid::from_raw(0));
+        unsafe {
+            cmd.pre_exec(move || nix::unistd::setpgid(Pid::from_raw(0), group)
+                .or_else(|_| nix::unistd::setpgid(Pid::from_raw(0), Pid::from_raw(0)))
+                .map_err(|_| std::io::Error::last_os_error()));
+        }
+    }
+    cmd.stdin(match input {
+        Value::Empty() => Stdio::inherit(),
+        _ => Stdio::piped(),
+    });
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let child = to_crush_error(cmd.spawn())?;
+    let pid = Pid::from_raw(child.id() as i32);
+    job_table::add_process(job_id, pid, background);
+    Ok((child, pid))
+}
+
+/**
+Receive the value piped into the external command. The empty table stream that
+the first command of a job gets as input, as well as an input that is not
+connected to anything, is treated as no input at all.
+*/
+fn read_input(input: &ValueReceiver) -> CrushResult<Value> {
+    match input.recv() {
+        Ok(Value::TableStream(mut s)) =>
+            match s.read() {
+                Ok(_) => argument_error("Can not pipe a table stream into an external command"),
+                Err(_) => Ok(Value::Empty()),
+            },
+        Ok(v) => Ok(v),
+        Err(_) => Ok(Value::Empty()),
+    }
+}
+
+fn write_stdin(input: Value, mut stdin: ChildStdin) -> CrushResult<()> {
+    let res = match input {
+        Value::BinaryStream(mut reader) => std::io::copy(reader.as_mut(), &mut stdin).map(|_| ()),
+        Value::Binary(data) => stdin.write_all(&data),
+        Value::String(s) => stdin.write_all(s.as_bytes()),
+        Value::Empty() => Ok(()),
+        v => return argument_error(
+            format!("Can not pipe a value of type {} into an external command",
+                    v.value_type().to_string()).as_str()),
+    };
+    match res {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        r => to_crush_error(r),
+    }
+}
+
+fn spawn_stdin_writer(input: Value, stdin: Option<ChildStdin>, printer: &Printer) -> CrushResult<()> {
+    if let Some(stdin) = stdin {
+        let printer = printer.clone();
+        to_crush_error(build("cmd:stdin").spawn(move || {
+            printer.handle_error(write_stdin(input, stdin));
+        }))?;
+    }
+    Ok(())
+}
+
+fn spawn_stderr_printer(stderr: Option<ChildStderr>, printer: &Printer) -> CrushResult<Option<JoinHandle<()>>> {
+    match stderr {
+        None => Ok(None),
+        Some(stderr) => {
+            let printer = printer.clone();
+            Ok(Some(to_crush_error(build("cmd:stderr").spawn(move || {
+                for line in BufReader::new(stderr).lines() {
+                    match line {
+                        Ok(line) => {
+                            let err = line.trim();
+                            if !err.is_empty() {
+                                printer.error(err);
+                            }
+                        }
+                        Err(_) => break,
+                    }
+                }
+            }))?))
+        }
+    }
+}
+
+/**
+Wait for the process to change state, like waitpid does, but also return the
+CPU time used by the process. The CPU time is only known once it has exited.
+*/
+pub fn wait(pid: Pid, flags: WaitPidFlag) -> nix::Result<(WaitStatus, Duration)> {
+    let mut status: nix::libc::c_int = 0;
+    let mut usage: nix::libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        let res = unsafe { nix::libc::wait4(pid.as_raw(), &mut status, flags.bits(), &mut usage) };
+        match Errno::result(res) {
+            Err(nix::Error::Sys(Errno::EINTR)) => {}
+            Err(e) => return Err(e),
+            Ok(_) => break,
+        }
+    }
+    let cpu_time = Duration::seconds((usage.ru_utime.tv_sec + usage.ru_stime.tv_sec) as i64)
+        + Duration::microseconds((usage.ru_utime.tv_usec + usage.ru_stime.tv_usec) as i64);
+    Ok((WaitStatus::from_raw(pid, status)?, cpu_time))
+}
+
+/**
+Wait for the process to exit. If it is stopped, e.g. because the user pressed
+Ctrl-Z while it had the terminal, the job it belongs to is suspended.
+*/
+fn wait_exit(pid: Pid, job_id: JobId) -> nix::Result<(WaitStatus, Duration)> {
+    loop {
+        match wait(pid, WaitPidFlag::WUNTRACED)? {
+            (WaitStatus::Stopped(_, _), _) => job_table::stopped(job_id),
+            res => return Ok(res),
+        }
+    }
+}
+
+/**
+Store the exit status of an external command in the `cmd_status` variable of
+the calling scope. If `cmd_fail_on_error` is set to true, a command that did
+not exit successfully is an error.
+*/
+fn report_status(name: &str, status: WaitStatus, started: Instant, cpu_time: Duration, env: &Scope) -> CrushResult<()> {
+    let (exit_code, signal) = match status {
+        WaitStatus::Exited(_, code) => (Value::Integer(code as i128), Value::Empty()),
+        WaitStatus::Signaled(_, signal, _) => (Value::Empty(), Value::String(signal.as_str().to_string())),
+        _ => (Value::Empty(), Value::Empty()),
+    };
+    let success = exit_code == Value::Integer(0);
+    env.redeclare("cmd_status", Value::Struct(Struct::new(
+        vec![
+            ("success".to_string(), Value::Bool(success)),
+            ("exit_code".to_string(), exit_code),
+            ("signal".to_string(), signal),
+            ("wall_time".to_string(), Value::Duration(to_crush_error(Duration::from_std(started.elapsed()))?)),
+            ("cpu_time".to_string(), Value::Duration(cpu_time)),
+        ],
+        None,
+    )))?;
+
+    if success {
+        return Ok(());
+    }
+    match env.get("cmd_fail_on_error")? {
+        Some(Value::Bool(true)) => match status {
+            WaitStatus::Exited(_, code) => error(&format!("{} exited with status {}", name, code)),
+            WaitStatus::Signaled(_, signal, _) => error(&format!("{} was killed by {}", name, signal.as_str())),
+            _ => error(&format!("{} did not exit normally", name)),
+        },
+        _ => Ok(()),
+    }
+}
+
+fn stream<T: IntoRawFd>(pipe: Option<T>) -> Value {
+    match pipe {
+        Some(pipe) => Value::BinaryStream(<dyn BinaryReader>::pipe(pipe)),
+        None => Value::Empty(),
+    }
+}
+
+pub fn cmd(context: ExecutionContext) -> CrushResult<()> {
+    let input = read_input(&context.input)?;
+    let name = context.arguments.first().map(|a| a.value.to_string()).unwrap_or_default();
+    let interactive = pty::is_interactive(&context, &name)?;
+    let command = build_command(context.arguments)?;
+    let started = Instant::now();
+    if interactive {
+        if let Value::Empty() = input {
+            let (status, cpu_time) = pty::run(command, context.job_id, &context.printer)?;
+            context.output.send(Value::Empty())?;
+            return report_status(&name, status, started, cpu_time, &context.env);
+        }
+    }
+
+    let (mut child, pid) = start(command, &input, context.job_id)?;
+    spawn_stdin_writer(input, child.stdin.take(), &context.printer)?;
+    let stderr_printer = spawn_stderr_printer(child.stderr.take(), &context.printer)?;
+    context.printer.handle_error(context.output.send(stream(child.stdout.take())));
+
+    let status = wait_exit(pid, context.job_id);
+    job_table::remove_process(context.job_id, pid);
+    if let Some(h) = stderr_printer {
+        let _ = h.join();
+    }
+    let (status, cpu_time) = to_crush_error(status)?;
+    report_status(&name, status, started, cpu_time, &context.env)
+}
+
+pub fn spawn(context: ExecutionContext) -> CrushResult<()> {
+    let input = read_input(&context.input)?;
+    let (mut child, pid) = start(build_command(context.arguments)?, &input, context.job_id)?;
+    spawn_stdin_writer(input, child.stdin.take(), &context.printer)?;
+    context.printer.handle_error(context.output.send(Value::Struct(Struct::new(
+        vec![
+            ("pid".to_string(), Value::Integer(pid.as_raw() as i128)),
+            ("stdout".to_string(), stream(child.stdout.take())),
+            ("stderr".to_string(), stream(child.stderr.take())),
+        ],
+        None,
+    ))));
+
+    // The output streams may be read long after this job has finished, so
+    // don't make the job wait for the process to exit.
+    let job_id = context.job_id;
+    to_crush_error(build("spawn:wait").spawn(move || {
+        let _ = child.wait();
+        job_table::remove_process(job_id, pid);
+    }))?;
+    Ok(())
+}
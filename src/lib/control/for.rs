@@ -41,6 +41,7 @@ pub fn run(
             env: env.clone(),
             this: None,
             printer: context.printer.clone(),
+            job_id: context.job_id,
         })?;
         if env.is_stopped() {
             break;
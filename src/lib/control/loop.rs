@@ -27,6 +27,7 @@ fn r#loop(context: ExecutionContext) -> CrushResult<()> {
             env: env.clone(),
             this: None,
             printer: context.printer.clone(),
+            job_id: context.job_id,
         })?;
         if env.is_stopped() {
             break;
@@ -0,0 +1,90 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::errors::{CrushResult, argument_error};
+use crate::lang::argument::{Argument, ArgumentHandler};
+use crate::lang::command::Command;
+use crate::lang::value::Value;
+use crate::lib::types::r#enum::variant;
+use signature::signature;
+
+#[signature(
+r#match,
+condition = true,
+short = "Execute the command of the first pattern that matches a value.",
+long = "The patterns and commands are given as pairs. A field matches enum values of the variant with that name, and the members of the payload are passed to the command as named arguments. A type matches values of that type, a glob or a regex matches strings and files and any other pattern matches values that are equal to it.",
+long = "Commands that declare parameters are passed the matched value. A regex also passes its capture groups as additional arguments.",
+example = "match shape ^circle {|radius| radius*radius*3.14} ^rect {|width height| width*height} default={0.0}")]
+pub struct Match {
+    #[description("the value to match.")]
+    subject: Value,
+    #[unnamed()]
+    #[description("pairs of patterns and the commands to invoke if they match.")]
+    arms: Vec<Value>,
+    #[description("the command to invoke if no pattern matches.")]
+    default: Option<Command>,
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::File(f) => f.to_str().map(|s| s.to_string()),
+        _ => None,
+    }
+}
+
+/**
+The arguments to pass to the command of the pattern, if the pattern matches
+the value.
+*/
+fn arguments(pattern: &Value, value: &Value) -> Option<Vec<Argument>> {
+    match pattern {
+        Value::Field(f) if f.len() == 1 => match variant(value) {
+            Some((name, payload)) if name == f[0] =>
+                Some(payload.into_iter().map(|(name, value)| Argument::named(&name, value)).collect()),
+            _ => None,
+        },
+        Value::Type(t) if t.is(value) => Some(vec![Argument::unnamed(value.clone())]),
+        Value::Glob(g) => text(value)
+            .filter(|s| g.matches(s))
+            .map(|_| vec![Argument::unnamed(value.clone())]),
+        Value::Regex(_, re) => text(value)
+            .and_then(|s| re.captures(&s).map(|c| {
+                c.iter()
+                    .skip(1)
+                    .map(|m| Argument::unnamed(m.map(|m| Value::string(m.as_str())).unwrap_or(Value::Empty())))
+                    .collect::<Vec<_>>()
+            }))
+            .map(|mut groups| {
+                groups.insert(0, Argument::unnamed(value.clone()));
+                groups
+            }),
+        Value::Type(_) => None,
+        pattern if pattern == value => Some(vec![Argument::unnamed(value.clone())]),
+        _ => None,
+    }
+}
+
+fn r#match(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Match = Match::parse(context.arguments.clone(), &context.printer)?;
+    if !cfg.arms.len().is_multiple_of(2) {
+        return argument_error("Expected pairs of patterns and commands");
+    }
+
+    for arm in cfg.arms.chunks(2) {
+        let command = match &arm[1] {
+            Value::Command(command) => command,
+            v => return argument_error(
+                format!("Expected a command, found {}", v.value_type().to_string()).as_str()),
+        };
+        if let Some(arguments) = arguments(&arm[0], &cfg.subject) {
+            let arguments = match (&arm[0], command.arguments().is_empty()) {
+                (Value::Field(_), _) | (_, false) => arguments,
+                _ => vec![],
+            };
+            return command.invoke(context.with_args(arguments, None));
+        }
+    }
+
+    cfg.default
+        .map(|v| v.invoke(context.with_args(vec![], None)))
+        .unwrap_or(Ok(()))
+}
@@ -1,6 +1,6 @@
 use crate::lang::scope::Scope;
-use crate::lang::errors::{CrushResult, argument_error, to_crush_error};
-use crate::lang::{value::Value, list::List, value::ValueType, execution_context::ExecutionContext, binary::BinaryReader};
+use crate::lang::errors::{CrushResult, to_crush_error};
+use crate::lang::{value::Value, list::List, value::ValueType, execution_context::ExecutionContext};
 use std::env;
 use signature::signature;
 
@@ -8,6 +8,11 @@ mod r#if;
 mod r#while;
 mod r#loop;
 mod r#for;
+mod r#try;
+mod r#match;
+pub mod module;
+mod cmd;
+mod pty;
 
 use std::path::PathBuf;
 use chrono::Duration;
@@ -24,49 +29,6 @@ pub fn r#continue(context: ExecutionContext) -> CrushResult<()> {
     context.output.empty()
 }
 
-pub fn cmd(mut context: ExecutionContext) -> CrushResult<()> {
-    if context.arguments.is_empty() {
-        return argument_error("No command given");
-    }
-    match context.arguments.remove(0).value {
-        Value::File(f) => {
-            let mut cmd = std::process::Command::new(f.as_os_str());
-            for a in context.arguments.drain(..) {
-                match a.argument_type {
-                    None => {
-                        cmd.arg(a.value.to_string());
-                    }
-                    Some(name) => {
-                        if name.len() == 1 {
-                            cmd.arg(format!("-{}", name));
-                        } else {
-                            cmd.arg(format!("--{}", name));
-                        }
-                        match a.value {
-                            Value::Bool(true) => {}
-                            _ => {
-                                cmd.arg(a.value.to_string());
-                            }
-                        }
-                    }
-                }
-            }
-            let output = to_crush_error(cmd.output())?;
-            let errors = String::from_utf8_lossy(&output.stderr);
-            for e in errors.split('\n') {
-                let err = e.trim();
-                if !err.is_empty() {
-                    context.printer.error(err);
-                }
-            }
-            context.output.send(
-                Value::BinaryStream(
-                    BinaryReader::vec(&output.stdout)))
-        }
-        _ => argument_error("Not a valid command")
-    }
-}
-
 #[signature(
 sleep,
 can_block = true,
@@ -97,9 +59,20 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
                 let _ = path.append(&mut dirs);
             }))?;
             env.declare("cmd_path", Value::List(path))?;
+            env.declare("cmd_fail_on_error", Value::Bool(false))?;
+            env.declare("cmd_pty", Value::List(List::new(ValueType::String, pty::default_commands())))?;
+            env.declare("module_path", Value::List(List::new(
+                ValueType::File,
+                module::default_path().into_iter().map(Value::File).collect())))?;
             r#if::If::declare(env)?;
             r#while::While::declare(env)?;
             r#loop::Loop::declare(env)?;
+            r#try::Try::declare(env)?;
+            r#try::Raise::declare(env)?;
+            r#match::Match::declare(env)?;
+            module::SourceFile::declare(env)?;
+            module::Import::declare(env)?;
+            module::Export::declare(env)?;
 
             env.declare_condition_command(
                 "for",
@@ -121,10 +94,61 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
                 "Skip execution of the current iteration of a loop",
                 None, Known(ValueType::Empty))?;
             env.declare_command(
-                "cmd", cmd, true,
+                "cmd", cmd::cmd, true,
                 "cmd external_command:(file|string) @arguments:any",
                 "Execute external commands",
-                None, Known(ValueType::BinaryStream))?;
+                Some(r#"    The input of the command is written to the standard input of the external
+    command. If nothing is piped into the command, it reads from the terminal
+    instead. The standard output is streamed as the output of the command.
+    Anything written to standard error is printed as an error as it arrives.
+
+    Named arguments are converted into options, e.g. foo=bar becomes --foo bar
+    and f=true becomes -f.
+
+    Once the external command has exited, the variable cmd_status is set to a
+    struct with the following members:
+
+    * success:bool true if the command exited with status 0
+
+    * exit_code:integer the exit status, or empty if the command was killed
+
+    * signal:string the signal that killed the command, if any
+
+    * wall_time:duration the time the command ran for
+
+    * cpu_time:duration the CPU time the command used
+
+    If the variable cmd_fail_on_error is true, a command that does not exit
+    successfully is an error, and no further jobs are run. Otherwise, errors
+    are printed and the next job is run. A closure fails if its last job fails.
+
+    Commands whose names are in the list cmd_pty, like vim and less, are run in
+    a pseudo terminal if they are the last command of a job running in the
+    foreground and nothing is piped into them.
+
+    Example:
+
+    cmd_fail_on_error := true
+    git pull
+    make"#), Known(ValueType::BinaryStream))?;
+            env.declare_command(
+                "spawn", cmd::spawn, true,
+                "spawn external_command:(file|string) @arguments:any",
+                "Start an external command without waiting for it to finish",
+                Some(r#"    The input of the command is written to the standard input of the external
+    command. If nothing is piped into the command, it reads from the terminal
+    instead. The output is a struct with the following members:
+
+    * pid:integer the process id of the external command
+
+    * stdout:binary_stream the standard output of the external command
+
+    * stderr:binary_stream the standard error of the external command
+
+    Example:
+
+    p := (spawn "make")
+    p:stderr | lines:from"#), Known(ValueType::Struct))?;
             Sleep::declare(env)?;
             Ok(())
         }))?;
@@ -0,0 +1,177 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::errors::{CrushResult, to_crush_error, argument_error, error, mandate};
+use crate::lang::argument::ArgumentHandler;
+use crate::lang::command::OutputType::Known;
+use crate::lang::execute;
+use crate::lang::files::Files;
+use crate::lang::list::List;
+use crate::lang::pretty_printer::create_pretty_printer;
+use crate::lang::printer::Printer;
+use crate::lang::scope::Scope;
+use crate::lang::source::Source;
+use crate::lang::stream::ValueSender;
+use crate::lang::value::{Value, ValueType};
+use signature::signature;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::fs;
+
+/**
+The modules of the standard library, which are compiled into the binary so
+that they are available regardless of where crush is started from.
+*/
+pub const STDLIB: &[(&str, &str)] = &[
+    ("lls", include_str!("../../crushlib/lls.crush")),
+];
+
+/**
+The name of the variable that holds the names exported by a module.
+*/
+const EXPORTS: &str = "__exports__";
+
+/**
+The default module search path, i.e. the user module directory followed by
+the system module directory.
+*/
+pub fn default_path() -> Vec<PathBuf> {
+    dirs::config_dir()
+        .map(|d| d.join("crush").join("modules"))
+        .into_iter()
+        .chain(Some(PathBuf::from("/usr/share/crush/modules")))
+        .collect()
+}
+
+pub fn stdlib(name: &str) -> Option<Arc<Source>> {
+    STDLIB.iter()
+        .find(|(n, _)| *n == name)
+        .map(|(n, text)| Source::file(Path::new(&format!("{}.crush", n)), text.to_string()))
+}
+
+/**
+Find a module in the directories of the search path, falling back to the
+standard library.
+*/
+fn find(name: &str, path: &[Value]) -> CrushResult<Option<Arc<Source>>> {
+    for dir in path {
+        let dir = match dir {
+            Value::File(f) => f.clone(),
+            Value::String(s) => PathBuf::from(s),
+            v => return error(
+                format!("Invalid element of module_path of type {}", v.value_type().to_string()).as_str()),
+        };
+        let file = dir.join(format!("{}.crush", name));
+        if file.is_file() {
+            return Ok(Some(Source::file(&file, to_crush_error(fs::read_to_string(&file))?)));
+        }
+    }
+    Ok(stdlib(name))
+}
+
+/**
+Declare a lazy namespace with the specified name in the scope. The module is
+run the first time the namespace is accessed, and the variables it exports
+become the members of the namespace. A module that doesn't export anything
+exports all of its variables. If a job of the module fails, every access to
+the namespace fails with its error.
+*/
+pub fn load(name: &str, source: Arc<Source>, env: &Scope, printer: &Printer, output: &ValueSender) -> CrushResult<Scope> {
+    let printer = printer.clone();
+    let output = output.clone();
+    env.create_lazy_namespace(name, Box::new(move |env| {
+        let tmp_env: Scope = env.create_temporary_namespace();
+        execute::module(tmp_env.clone(), &source, &printer, &output)?;
+        let mut data = tmp_env.export()?;
+        let exports = match data.mapping.remove(EXPORTS) {
+            Some(Value::List(l)) => Some(l.dump()),
+            _ => None,
+        };
+        match exports {
+            None =>
+                for (k, v) in data.mapping {
+                    env.declare(&k, v)?;
+                },
+            Some(exports) =>
+                for name in exports {
+                    let name = name.to_string();
+                    let value = mandate(
+                        data.mapping.remove(&name),
+                        format!("Exported variable {} is not defined", name).as_str())?;
+                    env.declare(&name, value)?;
+                },
+        }
+        Ok(())
+    }))
+}
+
+#[signature(
+source,
+can_block = true,
+short = "Run the specified files in the current scope",
+long = "Any variables declared by the files remain declared after they have been run, and the output of their jobs is printed.",
+example = "source ./aliases.crush")]
+pub struct SourceFile {
+    #[unnamed()]
+    #[description("the files to run.")]
+    files: Files,
+}
+
+fn source(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: SourceFile = SourceFile::parse(context.arguments, &context.printer)?;
+    let output = create_pretty_printer(context.printer.clone());
+    for file in cfg.files.into_vec() {
+        execute::file(context.env.clone(), &file, &context.printer, &output)?;
+    }
+    context.output.send(Value::Empty())
+}
+
+#[signature(
+import,
+can_block = false,
+output = Known(ValueType::Empty),
+short = "Import a module into a namespace of its own",
+long = "The module name.crush is searched for in the directories of the module_path variable, followed by the modules of the standard library. The module is run the first time the namespace is used.",
+example = "import \"lls\"")]
+pub struct Import {
+    #[unnamed()]
+    #[description("the names of the modules to import.")]
+    names: Vec<String>,
+}
+
+fn import(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Import = Import::parse(context.arguments, &context.printer)?;
+    let path = match context.env.get("module_path")? {
+        Some(Value::List(l)) => l.dump(),
+        _ => vec![],
+    };
+    for name in cfg.names {
+        let source = mandate(find(&name, &path)?, format!("Unknown module {}", name).as_str())?;
+        load(&name, source, &context.env, &context.printer, &create_pretty_printer(context.printer.clone()))?;
+    }
+    context.output.send(Value::Empty())
+}
+
+#[signature(
+export,
+can_block = false,
+output = Known(ValueType::Empty),
+short = "Declare the variables that a module exports",
+long = "Only the exported variables of a module become members of its namespace. If a module doesn't export anything, all of its variables are exported.",
+example = "export \"ls\" \"ll\"")]
+pub struct Export {
+    #[unnamed()]
+    #[description("the names of the variables to export.")]
+    names: Vec<String>,
+}
+
+fn export(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Export = Export::parse(context.arguments, &context.printer)?;
+    if cfg.names.iter().any(|n| n.is_empty()) {
+        return argument_error("Illegal variable name");
+    }
+    let mut names = cfg.names.iter().map(|n| Value::string(n)).collect();
+    match context.env.export()?.mapping.get(EXPORTS) {
+        Some(Value::List(l)) => l.append(&mut names)?,
+        _ => context.env.declare(EXPORTS, Value::List(List::new(ValueType::String, names)))?,
+    }
+    context.output.send(Value::Empty())
+}
@@ -0,0 +1,242 @@
+use crate::lang::errors::{CrushResult, to_crush_error};
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::printer::Printer;
+use crate::lang::job_table::{self, JobId};
+use crate::lang::value::Value;
+use crate::util::thread::build;
+use nix::pty::{openpty, Winsize};
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::signal::{self, Signal, SigHandler, SigAction, SaFlags, SigSet};
+use nix::sys::wait::{WaitPidFlag, WaitStatus};
+use nix::sys::termios::{tcgetattr, LocalFlags, SpecialCharacterIndices};
+use nix::unistd::{Pid, isatty, setsid, dup, read};
+use nix::errno::Errno;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::unix::io::{FromRawFd, AsRawFd};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use termion::raw::IntoRawMode;
+use chrono::Duration;
+use super::cmd::wait;
+
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_resize(_: nix::libc::c_int) {
+    RESIZED.store(true, Ordering::SeqCst);
+}
+
+nix::ioctl_write_ptr_bad!(set_window_size, nix::libc::TIOCSWINSZ, Winsize);
+
+fn window_size(printer: &Printer) -> Winsize {
+    Winsize {
+        ws_row: printer.height() as u16,
+        ws_col: printer.width() as u16,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+/**
+Returns true if an external command should be run in a pseudo terminal, i.e.
+if it is listed in the `cmd_pty` variable and it is the last command of a
+foreground job, so that its output would go straight to the terminal anyway.
+*/
+pub fn is_interactive(context: &ExecutionContext, name: &str) -> CrushResult<bool> {
+    if !context.output.is_terminal()
+        || job_table::is_background(context.job_id)
+        || !isatty(0).unwrap_or(false)
+        || !isatty(1).unwrap_or(false) {
+        return Ok(false);
+    }
+    let name = Path::new(name).file_name().and_then(|n| n.to_str()).unwrap_or(name);
+    Ok(match context.env.get("cmd_pty")? {
+        Some(Value::List(commands)) => commands.dump().iter().any(|c| c.to_string() == name),
+        _ => false,
+    })
+}
+
+/**
+The default value of the `cmd_pty` variable, i.e. common programs that need a
+terminal.
+*/
+pub fn default_commands() -> Vec<Value> {
+    ["vi", "vim", "nvim", "nano", "emacs", "less", "more", "man", "top", "htop", "ssh"].iter()
+        .map(|c| Value::string(c))
+        .collect()
+}
+
+/**
+Returns true if the input contains the character that the terminal settings of
+the pseudo terminal say should suspend the command.
+*/
+fn contains_suspend(master: &File, input: &[u8]) -> bool {
+    match tcgetattr(master.as_raw_fd()) {
+        Ok(attr) if attr.local_flags.contains(LocalFlags::ISIG) => {
+            let suspend = attr.control_chars[SpecialCharacterIndices::VSUSP as usize];
+            suspend != 0 && input.contains(&suspend)
+        }
+        _ => false,
+    }
+}
+
+/**
+Copy keyboard input to the pseudo terminal until told to stop. Terminal
+resizes are forwarded from here as well.
+
+The command is the leader of its own session, so its process group is orphaned
+and the kernel discards the SIGTSTP that the pseudo terminal sends when the
+suspend character is typed. If the command hasn't turned off signal generating
+characters, it is instead stopped from here using SIGSTOP.
+*/
+fn forward_input(master: &File, pid: Pid, printer: &Printer) -> CrushResult<(Arc<AtomicBool>, JoinHandle<()>)> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_clone = stop.clone();
+    let mut master = to_crush_error(master.try_clone())?;
+    let printer = printer.clone();
+    let handle = to_crush_error(build("pty:input").spawn(move || {
+        let mut buff = [0u8; 4096];
+        while !stop_clone.load(Ordering::SeqCst) {
+            if RESIZED.swap(false, Ordering::SeqCst) {
+                unsafe {
+                    let _ = set_window_size(master.as_raw_fd(), &window_size(&printer));
+                }
+            }
+            let mut fds = [PollFd::new(0, PollFlags::POLLIN)];
+            match poll(&mut fds, 100) {
+                Ok(0) => {}
+                Ok(_) => match read(0, &mut buff) {
+                    Ok(0) | Err(_) => break,
+                    Ok(len) => {
+                        if master.write_all(&buff[0..len]).is_err() {
+                            break;
+                        }
+                        if contains_suspend(&master, &buff[0..len]) {
+                            let _ = signal::kill(pid, Signal::SIGSTOP);
+                            break;
+                        }
+                    }
+                },
+                Err(nix::Error::Sys(Errno::EINTR)) => {}
+                Err(_) => break,
+            }
+        }
+    }))?;
+    Ok((stop, handle))
+}
+
+fn forward_output(master: &File) -> CrushResult<JoinHandle<()>> {
+    let mut master = to_crush_error(master.try_clone())?;
+    to_crush_error(build("pty:output").spawn(move || {
+        let mut stdout = std::io::stdout();
+        let mut buff = [0u8; 4096];
+        loop {
+            match master.read(&mut buff) {
+                Ok(0) | Err(_) => break,
+                Ok(len) => {
+                    if stdout.write_all(&buff[0..len]).is_err() {
+                        break;
+                    }
+                    let _ = stdout.flush();
+                }
+            }
+        }
+    }))
+}
+
+/**
+Run an external command in a pseudo terminal and wait for it to exit. The
+terminal is put in raw mode while the command runs, so that key presses,
+including Ctrl-C and Ctrl-Z, reach the command unaltered.
+
+If the command is stopped, the job it belongs to is suspended. When the job is
+resumed in the foreground, the command takes over the terminal again.
+
+Returns the exit status and CPU time of the command.
+*/
+pub fn run(mut cmd: Command, job_id: JobId, printer: &Printer) -> CrushResult<(WaitStatus, Duration)> {
+    let pty = to_crush_error(openpty(&window_size(printer), None))?;
+    let master = unsafe { File::from_raw_fd(pty.master) };
+    unsafe {
+        cmd.stdin(Stdio::from_raw_fd(to_crush_error(dup(pty.slave))?))
+            .stdout(Stdio::from_raw_fd(to_crush_error(dup(pty.slave))?))
+            .stderr(Stdio::from_raw_fd(pty.slave));
+        cmd.pre_exec(|| {
+            setsid().map_err(|_| std::io::Error::last_os_error())?;
+            if nix::libc::ioctl(0, nix::libc::TIOCSCTTY, 0) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let child = cmd.spawn();
+    // The child has its own copy of the slave side, and the output forwarder
+    // relies on reads failing once the child exits.
+    drop(cmd);
+    let child = to_crush_error(child)?;
+    let pid = Pid::from_raw(child.id() as i32);
+    job_table::add_process(job_id, pid, false);
+
+    let resize = SigAction::new(
+        SigHandler::Handler(handle_resize),
+        SaFlags::SA_RESTART,
+        SigSet::empty());
+    let previous_resize = unsafe {
+        to_crush_error(signal::sigaction(Signal::SIGWINCH, &resize))?
+    };
+    let status = supervise(&master, pid, job_id, printer);
+    unsafe {
+        let _ = signal::sigaction(Signal::SIGWINCH, &previous_resize);
+    }
+    job_table::remove_process(job_id, pid);
+    status
+}
+
+/**
+Forward input and output between the terminal and the pseudo terminal until
+the command exits.
+*/
+fn supervise(master: &File, pid: Pid, job_id: JobId, printer: &Printer) -> CrushResult<(WaitStatus, Duration)> {
+    let output = forward_output(master)?;
+    let mut raw = Some(to_crush_error(std::io::stdout().into_raw_mode())?);
+    let mut input = Some(forward_input(master, pid, printer)?);
+
+    let status = loop {
+        match wait(pid, WaitPidFlag::WUNTRACED | WaitPidFlag::WCONTINUED) {
+            Ok((WaitStatus::Stopped(_, _), _)) => {
+                if let Some((stop, handle)) = input.take() {
+                    stop.store(true, Ordering::SeqCst);
+                    let _ = handle.join();
+                }
+                raw = None;
+                job_table::stopped(job_id);
+            }
+            Ok((WaitStatus::Continued(_), _)) => {
+                if job_table::is_background(job_id) {
+                    // Interactive commands can't run without the terminal
+                    let _ = signal::kill(pid, Signal::SIGSTOP);
+                } else if raw.is_none() {
+                    raw = Some(to_crush_error(std::io::stdout().into_raw_mode())?);
+                    RESIZED.store(true, Ordering::SeqCst);
+                    input = Some(forward_input(master, pid, printer)?);
+                    // Most interactive programs redraw the screen on SIGWINCH
+                    let _ = signal::kill(pid, Signal::SIGWINCH);
+                }
+            }
+            Ok(s @ (WaitStatus::Exited(_, _), _)) | Ok(s @ (WaitStatus::Signaled(_, _, _), _)) => break Ok(s),
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+    };
+    if let Some((stop, handle)) = input.take() {
+        stop.store(true, Ordering::SeqCst);
+        let _ = handle.join();
+    }
+    drop(raw);
+    let _ = output.join();
+    to_crush_error(status)
+}
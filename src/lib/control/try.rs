@@ -0,0 +1,89 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::errors::{CrushResult, CrushError, user_error, to_crush_error};
+use crate::lang::value::Value;
+use crate::lang::argument::{Argument, ArgumentHandler};
+use crate::lang::command::Command;
+use crate::lang::r#struct::Struct;
+use crate::lang::stream::{channels, black_hole};
+use crate::util::thread::build;
+use signature::signature;
+
+#[signature(
+r#try,
+condition = true,
+short = "Execute a command and handle any error it raises.",
+long = "The body stops at the first job that fails, in which case the catch clause is invoked with a\n    struct describing the error as its only argument. The struct has the members kind, message and\n    command. The finally clause is invoked last of all, whether or not an error occurred.",
+example = "try {cmd \"make\"} catch={|err| echo err:message} finally={rm ./build.lock}")]
+pub struct Try {
+    #[description("the command to invoke.")]
+    body: Command,
+    #[description("the command to invoke if the body fails. If missing, the error is ignored.")]
+    catch: Option<Command>,
+    #[description("the command to invoke after the body and catch clause.")]
+    finally: Option<Command>,
+}
+
+fn error_struct(err: &CrushError) -> Value {
+    Value::Struct(Struct::new(
+        vec![
+            ("kind".to_string(), Value::string(&err.kind.to_string())),
+            ("message".to_string(), Value::string(&err.message)),
+            ("command".to_string(), err.command.as_ref().map(|c| Value::string(c)).unwrap_or(Value::Empty())),
+        ],
+        None,
+    ))
+}
+
+fn r#try(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Try = Try::parse(context.arguments.clone(), &context.printer)?;
+
+    // Keep track of whether the body produced any output, so that the catch
+    // clause knows whether it is still expected to.
+    let (sender, receiver) = channels();
+    let output = context.output.clone();
+    let forwarder = to_crush_error(build("try:output").spawn(move || {
+        match receiver.recv() {
+            Ok(value) => output.send(value).is_ok(),
+            Err(_) => false,
+        }
+    }))?;
+
+    let mut body_context = context.clone().with_sender(sender).with_args(vec![], None);
+    body_context.env = context.env.create_try_child();
+    let res = cfg.body.invoke(body_context);
+    let sent = forwarder.join().unwrap_or(true);
+    let output = if sent { black_hole() } else { context.output.clone() };
+
+    let res = match (res, cfg.catch) {
+        (Ok(_), _) => Ok(()),
+        (Err(err), Some(catch)) =>
+            catch.invoke(context.clone().with_sender(output)
+                .with_args(vec![Argument::unnamed(error_struct(&err))], None)),
+        (Err(_), None) => output.empty(),
+    };
+
+    match cfg.finally {
+        Some(finally) => {
+            let finally_res = finally.invoke(
+                context.with_sender(black_hole()).with_args(vec![], None));
+            res.and(finally_res)
+        }
+        None => res,
+    }
+}
+
+#[signature(
+raise,
+can_block = false,
+short = "Raise an error.",
+long = "The error can be handled using the catch clause of the try command.",
+example = "raise \"Could not find the configuration file\"")]
+pub struct Raise {
+    #[description("the error message.")]
+    message: String,
+}
+
+fn raise(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Raise = Raise::parse(context.arguments, &context.printer)?;
+    user_error(&cfg.message)
+}
@@ -34,6 +34,7 @@ fn r#while(context: ExecutionContext) -> CrushResult<()> {
             env: cond_env.clone(),
             this: None,
             printer: context.printer.clone(),
+            job_id: context.job_id,
         })?;
         if cond_env.is_stopped() {
             break;
@@ -51,6 +52,7 @@ fn r#while(context: ExecutionContext) -> CrushResult<()> {
                             env: body_env.clone(),
                             this: None,
                             printer: context.printer.clone(),
+                            job_id: context.job_id,
                         })?;
                         if body_env.is_stopped() {
                             break;
@@ -0,0 +1,91 @@
+use crate::lang::errors::{CrushResult, to_crush_error, argument_error};
+use crate::lang::files::Files;
+use crate::lang::stream::ValueReceiver;
+use crate::lang::binary::BinaryReader;
+use crate::lang::table::ColumnType;
+use crate::lang::value::ValueType;
+use crate::util::file::cwd;
+use std::io::{Read, Cursor};
+use std::path::{PathBuf, Path, Component};
+use std::sync::Arc;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    pub static ref OUTPUT_TYPE: Vec<ColumnType> = vec![
+        ColumnType::new("size", ValueType::Integer),
+        ColumnType::new("modified", ValueType::Time),
+        ColumnType::new("type", ValueType::String),
+        ColumnType::new("file", ValueType::File),
+        // Directories have no data, so the column may be empty
+        ColumnType::new("data", ValueType::Any),
+    ];
+}
+
+/**
+Where an archive is read from. Archives read from a file are opened again
+whenever the contents of an entry are read, archives read from io are kept in
+memory.
+*/
+#[derive(Clone)]
+pub enum Source {
+    File(PathBuf),
+    Memory(Arc<[u8]>),
+}
+
+impl Source {
+    pub fn new(files: Files, input: ValueReceiver) -> CrushResult<Source> {
+        if files.had_entries() {
+            let mut files = files.into_vec();
+            if files.len() != 1 {
+                return argument_error("Expected exactly one archive");
+            }
+            Ok(Source::File(files.remove(0)))
+        } else {
+            let mut data = Vec::new();
+            to_crush_error(files.reader(input)?.read_to_end(&mut data))?;
+            Ok(Source::Memory(Arc::from(data)))
+        }
+    }
+
+    /**
+    A reader for the archive. Compressed archive files are decompressed.
+    */
+    pub fn reader(&self) -> CrushResult<Box<dyn Read + Send>> {
+        match self {
+            Source::File(path) => Ok(<dyn BinaryReader>::paths(vec![path.clone()], true)?),
+            Source::Memory(data) => Ok(Box::from(Cursor::new(data.clone()))),
+        }
+    }
+}
+
+/**
+The directory to extract an archive into, which defaults to the current
+working directory.
+*/
+pub fn destination(directory: Files) -> CrushResult<PathBuf> {
+    if directory.had_entries() {
+        let mut directory = directory.into_vec();
+        if directory.len() != 1 {
+            return argument_error("Expected exactly one destination directory");
+        }
+        Ok(directory.remove(0))
+    } else {
+        cwd()
+    }
+}
+
+/**
+The name of a file inside an archive, i.e. the path without any leading
+root or current directory components.
+*/
+pub fn entry_name(path: &Path) -> CrushResult<PathBuf> {
+    let mut name = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(c) => name.push(c),
+            Component::ParentDir => return argument_error("Archive entries can't refer to a parent directory"),
+            _ => {}
+        }
+    }
+    Ok(name)
+}
@@ -0,0 +1,364 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::value::{Value, ValueType};
+use std::io::{BufRead, BufReader, Read, Cursor};
+
+use crate::lang::{r#struct::Struct, list::List, dict::Dict, table::Row};
+use crate::lang::errors::{CrushResult, to_crush_error, error, mandate};
+use crate::lang::table::ColumnType;
+use std::collections::HashMap;
+use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::Unknown;
+use crate::lang::files::Files;
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+use chrono::{Local, TimeZone};
+use flate2::read::DeflateDecoder;
+
+const MAGIC: &[u8] = b"Obj\x01";
+
+#[derive(Clone, Debug)]
+enum Schema {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    /** A long counting this many nanoseconds since the epoch */
+    Timestamp(i64),
+    Record(Vec<(String, Schema)>),
+    Enum(Vec<String>),
+    Array(Box<Schema>),
+    Map(Box<Schema>),
+    Union(Vec<Schema>),
+    Fixed(usize),
+    /** A reference to a named type, which is looked up when used so that types can be recursive */
+    Named(String),
+}
+
+impl Schema {
+    fn value_type(&self, names: &HashMap<String, Schema>) -> ValueType {
+        match self {
+            Schema::Null => ValueType::Empty,
+            Schema::Boolean => ValueType::Bool,
+            Schema::Int | Schema::Long => ValueType::Integer,
+            Schema::Float | Schema::Double => ValueType::Float,
+            Schema::Bytes | Schema::Fixed(_) => ValueType::Binary,
+            Schema::String | Schema::Enum(_) => ValueType::String,
+            Schema::Timestamp(_) => ValueType::Time,
+            Schema::Record(_) => ValueType::Struct,
+            Schema::Array(t) => ValueType::List(Box::from(t.value_type(names))),
+            Schema::Map(t) => ValueType::Dict(Box::from(ValueType::String), Box::from(t.value_type(names))),
+            // A union with null, i.e. an optional value, may be empty
+            Schema::Union(types) => match &types[..] {
+                [t] => t.value_type(names),
+                _ => ValueType::Any,
+            },
+            Schema::Named(name) => names.get(name).map(|s| s.value_type(names)).unwrap_or(ValueType::Any),
+        }
+    }
+}
+
+fn full_name(name: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(namespace) if !name.contains('.') && !namespace.is_empty() => format!("{}.{}", namespace, name),
+        _ => name.to_string(),
+    }
+}
+
+/**
+Parse the json representation of an avro schema. Named types are recorded
+in names as they are defined, and references to them are resolved when the
+values are read. A record is recorded before its fields are parsed, so that
+the fields can refer to the record itself.
+*/
+fn parse_schema(
+    json: &serde_json::Value,
+    namespace: Option<&str>,
+    names: &mut HashMap<String, Schema>,
+) -> CrushResult<Schema> {
+    match json {
+        serde_json::Value::String(name) => match name.as_str() {
+            "null" => Ok(Schema::Null),
+            "boolean" => Ok(Schema::Boolean),
+            "int" => Ok(Schema::Int),
+            "long" => Ok(Schema::Long),
+            "float" => Ok(Schema::Float),
+            "double" => Ok(Schema::Double),
+            "bytes" => Ok(Schema::Bytes),
+            "string" => Ok(Schema::String),
+            name => {
+                let full = full_name(name, namespace);
+                if names.contains_key(&full) {
+                    Ok(Schema::Named(full))
+                } else if names.contains_key(name) {
+                    Ok(Schema::Named(name.to_string()))
+                } else {
+                    error(format!("avro: Unknown type {}", name).as_str())
+                }
+            }
+        },
+        serde_json::Value::Array(types) =>
+            Ok(Schema::Union(
+                types.iter()
+                    .map(|t| parse_schema(t, namespace, names))
+                    .collect::<CrushResult<Vec<_>>>()?)),
+        serde_json::Value::Object(o) => {
+            let type_name = mandate(o.get("type"), "avro: Missing type in schema")?;
+            let name = match type_name.as_str() {
+                Some("record") | Some("error") | Some("enum") | Some("fixed") => o.get("name").and_then(|n| n.as_str()),
+                _ => None,
+            };
+            let namespace = o.get("namespace").and_then(|n| n.as_str()).or(namespace);
+            if let Some(name) = name {
+                // Replaced by the actual schema once it has been parsed
+                names.insert(full_name(name, namespace), Schema::Null);
+                names.insert(name.to_string(), Schema::Null);
+            }
+            let schema = match (type_name.as_str(), o.get("logicalType").and_then(|t| t.as_str())) {
+                (Some("long"), Some("timestamp-millis")) => Schema::Timestamp(1_000_000),
+                (Some("long"), Some("timestamp-micros")) => Schema::Timestamp(1_000),
+                (Some("record"), _) | (Some("error"), _) => {
+                    let fields = mandate(o.get("fields").and_then(|f| f.as_array()), "avro: Missing fields in record")?;
+                    Schema::Record(
+                        fields.iter()
+                            .map(|f| Ok((
+                                mandate(f.get("name").and_then(|n| n.as_str()), "avro: Missing field name")?.to_string(),
+                                parse_schema(mandate(f.get("type"), "avro: Missing field type")?, namespace, names)?)))
+                            .collect::<CrushResult<Vec<_>>>()?)
+                }
+                (Some("enum"), _) =>
+                    Schema::Enum(
+                        mandate(o.get("symbols").and_then(|f| f.as_array()), "avro: Missing symbols in enum")?
+                            .iter()
+                            .map(|s| Ok(mandate(s.as_str(), "avro: Invalid enum symbol")?.to_string()))
+                            .collect::<CrushResult<Vec<_>>>()?),
+                (Some("array"), _) =>
+                    Schema::Array(Box::from(parse_schema(mandate(o.get("items"), "avro: Missing items in array")?, namespace, names)?)),
+                (Some("map"), _) =>
+                    Schema::Map(Box::from(parse_schema(mandate(o.get("values"), "avro: Missing values in map")?, namespace, names)?)),
+                (Some("fixed"), _) =>
+                    Schema::Fixed(mandate(o.get("size").and_then(|s| s.as_u64()), "avro: Missing size in fixed")? as usize),
+                _ => parse_schema(type_name, namespace, names)?,
+            };
+            if let Some(name) = name {
+                names.insert(full_name(name, namespace), schema.clone());
+                names.insert(name.to_string(), schema.clone());
+            }
+            Ok(schema)
+        }
+        _ => error("avro: Invalid schema"),
+    }
+}
+
+fn read_long(reader: &mut dyn Read) -> CrushResult<i64> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0u8];
+        to_crush_error(reader.read_exact(&mut byte))?;
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(((value >> 1) as i64) ^ -((value & 1) as i64));
+        }
+        shift += 7;
+        if shift > 63 {
+            return error("avro: Invalid variable length integer");
+        }
+    }
+}
+
+fn read_fixed(reader: &mut dyn Read, len: usize) -> CrushResult<Vec<u8>> {
+    // Lengths come from the file, so the buffer only grows as data is
+    // actually read instead of being allocated up front
+    let mut buf = Vec::new();
+    to_crush_error(reader.take(len as u64).read_to_end(&mut buf))?;
+    if buf.len() != len {
+        return error("avro: Unexpected end of data");
+    }
+    Ok(buf)
+}
+
+fn read_bytes(reader: &mut dyn Read) -> CrushResult<Vec<u8>> {
+    let len = read_long(reader)?;
+    if len < 0 {
+        return error("avro: Negative length");
+    }
+    read_fixed(reader, len as usize)
+}
+
+fn read_string(reader: &mut dyn Read) -> CrushResult<String> {
+    to_crush_error(String::from_utf8(read_bytes(reader)?))
+}
+
+/**
+Arrays and maps are encoded as a series of blocks, each starting with its
+number of items. A negative count is followed by the size of the block in bytes.
+*/
+fn read_blocks(reader: &mut dyn Read, mut item: impl FnMut(&mut dyn Read) -> CrushResult<()>) -> CrushResult<()> {
+    loop {
+        let count = match read_long(reader)? {
+            0 => return Ok(()),
+            count if count < 0 => {
+                read_long(reader)?;
+                mandate(count.checked_neg(), "avro: Invalid block count")?
+            }
+            count => count,
+        };
+        for _ in 0..count {
+            item(reader)?;
+        }
+    }
+}
+
+fn read_record(reader: &mut dyn Read, fields: &[(String, Schema)], names: &HashMap<String, Schema>) -> CrushResult<Vec<Value>> {
+    fields.iter()
+        .map(|(_, schema)| read_value(reader, schema, names))
+        .collect()
+}
+
+fn read_value(reader: &mut dyn Read, schema: &Schema, names: &HashMap<String, Schema>) -> CrushResult<Value> {
+    Ok(match schema {
+        Schema::Null => Value::Empty(),
+        Schema::Boolean => Value::Bool(read_fixed(reader, 1)?[0] != 0),
+        Schema::Int | Schema::Long => Value::Integer(read_long(reader)? as i128),
+        Schema::Float => {
+            let mut buf = [0u8; 4];
+            to_crush_error(reader.read_exact(&mut buf))?;
+            Value::Float(f32::from_le_bytes(buf) as f64)
+        }
+        Schema::Double => {
+            let mut buf = [0u8; 8];
+            to_crush_error(reader.read_exact(&mut buf))?;
+            Value::Float(f64::from_le_bytes(buf))
+        }
+        Schema::Bytes => Value::Binary(read_bytes(reader)?),
+        Schema::Fixed(len) => Value::Binary(read_fixed(reader, *len)?),
+        Schema::String => Value::string(read_string(reader)?.as_str()),
+        Schema::Timestamp(nanos) => Value::Time(Local.timestamp_nanos(
+            mandate(read_long(reader)?.checked_mul(*nanos), "avro: Timestamp out of range")?)),
+        Schema::Enum(symbols) => {
+            let idx = read_long(reader)?;
+            Value::string(mandate(symbols.get(idx as usize), "avro: Invalid enum index")?)
+        }
+        Schema::Union(types) => {
+            let idx = read_long(reader)?;
+            read_value(reader, mandate(types.get(idx as usize), "avro: Invalid union index")?, names)?
+        }
+        Schema::Record(fields) =>
+            Value::Struct(Struct::new(
+                fields.iter()
+                    .map(|(name, schema)| Ok((name.clone(), read_value(reader, schema, names)?)))
+                    .collect::<CrushResult<Vec<_>>>()?,
+                None)),
+        Schema::Array(item_type) => {
+            let mut items = Vec::new();
+            read_blocks(reader, |r| {
+                items.push(read_value(r, item_type, names)?);
+                Ok(())
+            })?;
+            Value::List(List::new(item_type.value_type(names), items))
+        }
+        Schema::Map(value_type) => {
+            let dict = Dict::new(ValueType::String, value_type.value_type(names));
+            read_blocks(reader, |r| {
+                let key = Value::string(read_string(r)?.as_str());
+                dict.insert(key, read_value(r, value_type, names)?)
+            })?;
+            Value::Dict(dict)
+        }
+        Schema::Named(name) => read_value(reader, mandate(names.get(name), "avro: Unknown type")?, names)?,
+    })
+}
+
+#[signature(
+from,
+can_block = true,
+output = Unknown,
+short = "Parse avro object container files",
+long = "The schema is read from the file header. If the schema is a record, the output is a table stream with one column per field, otherwise it is a list.",
+long = "The null and deflate codecs are supported.",
+example = "avro:from ./events.avro | where {kind == \"click\"}")]
+struct From {
+    #[unnamed()]
+    #[description("source. If unspecified, will read from io.")]
+    files: Files,
+}
+
+fn from(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: From = From::parse(context.arguments, &context.printer)?;
+    let mut reader = BufReader::new(cfg.files.reader(context.input)?);
+
+    if read_fixed(&mut reader, MAGIC.len())? != MAGIC {
+        return error("avro: Not an avro object container file");
+    }
+    let mut metadata = HashMap::new();
+    read_blocks(&mut reader, |r| {
+        let key = read_string(r)?;
+        metadata.insert(key, read_bytes(r)?);
+        Ok(())
+    })?;
+    let sync = read_fixed(&mut reader, 16)?;
+
+    let schema_json = to_crush_error(serde_json::from_slice::<serde_json::Value>(
+        mandate(metadata.get("avro.schema"), "avro: Missing schema")?))?;
+    let mut names = HashMap::new();
+    let schema = parse_schema(&schema_json, None, &mut names)?;
+    let codec = metadata.get("avro.codec").map(|c| c.as_slice()).unwrap_or(b"null");
+    let deflate = match codec {
+        b"null" => false,
+        b"deflate" => true,
+        _ => return error(format!("avro: Unsupported codec {}", String::from_utf8_lossy(codec)).as_str()),
+    };
+
+    let output = match &schema {
+        Schema::Record(fields) => Some(context.output.initialize(
+            fields.iter()
+                .map(|(name, schema)| ColumnType::new(name, schema.value_type(&names)))
+                .collect())?),
+        _ => None,
+    };
+    let mut values = Vec::new();
+
+    loop {
+        if to_crush_error(reader.fill_buf())?.is_empty() {
+            break;
+        }
+        let count = read_long(&mut reader)?;
+        let data = read_bytes(&mut reader)?;
+        if read_fixed(&mut reader, 16)? != sync {
+            return error("avro: Invalid sync marker");
+        }
+        let mut block: Box<dyn Read> = if deflate {
+            Box::from(DeflateDecoder::new(Cursor::new(data)))
+        } else {
+            Box::from(Cursor::new(data))
+        };
+        for _ in 0..count {
+            match (&output, &schema) {
+                (Some(output), Schema::Record(fields)) =>
+                    if output.send(Row::new(read_record(&mut block, fields, &names)?)).is_err() {
+                        return Ok(());
+                    },
+                _ => values.push(read_value(&mut block, &schema, &names)?),
+            }
+        }
+    }
+
+    match output {
+        Some(_) => Ok(()),
+        None => context.output.send(Value::List(List::new(schema.value_type(&names), values))),
+    }
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "avro",
+        Box::new(move |env| {
+            From::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
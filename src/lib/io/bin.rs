@@ -11,7 +11,8 @@ use crate::lang::scope::ScopeLoader;
 #[signature(
 from,
 can_block = true,
-short = "Read specified files (or input) as a binary stream")]
+short = "Read specified files (or input) as a binary stream",
+long = "Compressed files are read as is. Use e.g. gzip:from to decompress them.")]
 struct From {
     #[unnamed()]
     files: Files,
@@ -19,7 +20,7 @@ struct From {
 
 pub fn from(context: ExecutionContext) -> CrushResult<()> {
     let cfg: From = From::parse(context.arguments, &context.printer)?;
-    context.output.send(Value::BinaryStream(cfg.files.reader(context.input)?))
+    context.output.send(Value::BinaryStream(cfg.files.raw_reader(context.input)?))
 }
 
 #[signature(
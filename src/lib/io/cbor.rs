@@ -0,0 +1,181 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::value::Value;
+use std::io::{BufReader, Read, Write};
+
+use crate::lang::{r#struct::Struct, dict::Dict};
+use crate::lang::errors::{CrushResult, to_crush_error, error, mandate};
+use std::collections::BTreeMap;
+use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::Unknown;
+use crate::lang::files::Files;
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+use crate::lib::io::document::{common_type, list, seconds, Seconds};
+
+fn from_cbor(cbor_value: serde_cbor::Value) -> CrushResult<Value> {
+    match cbor_value {
+        serde_cbor::Value::Null => Ok(Value::Empty()),
+        serde_cbor::Value::Bool(b) => Ok(Value::Bool(b)),
+        serde_cbor::Value::Integer(i) => Ok(Value::Integer(i)),
+        serde_cbor::Value::Float(f) => Ok(Value::Float(f)),
+        serde_cbor::Value::Text(s) => Ok(Value::string(s.as_str())),
+        serde_cbor::Value::Bytes(b) => Ok(Value::Binary(b)),
+        serde_cbor::Value::Array(arr) => Ok(list(
+            arr.into_iter()
+                .map(from_cbor)
+                .collect::<CrushResult<Vec<Value>>>()?)),
+        serde_cbor::Value::Map(m) => {
+            if m.keys().all(|k| matches!(k, serde_cbor::Value::Text(_))) {
+                Ok(Value::Struct(
+                    Struct::new(
+                        m
+                            .into_iter()
+                            .map(|(k, v)| match k {
+                                serde_cbor::Value::Text(k) => Ok((k, from_cbor(v)?)),
+                                _ => error("Impossible!"),
+                            })
+                            .collect::<CrushResult<Vec<(String, Value)>>>()?,
+                        None,
+                    )))
+            } else {
+                let (keys, values): (Vec<_>, Vec<_>) = m.into_iter().unzip();
+                let keys = keys.into_iter().map(from_cbor).collect::<CrushResult<Vec<Value>>>()?;
+                let values = values.into_iter().map(from_cbor).collect::<CrushResult<Vec<Value>>>()?;
+                let key_type = common_type(&keys);
+                if !key_type.is_hashable() {
+                    return error("Unsupported map key type");
+                }
+                let dict = Dict::new(key_type, common_type(&values));
+                for (k, v) in keys.into_iter().zip(values) {
+                    dict.insert(k, v)?;
+                }
+                Ok(Value::Dict(dict))
+            }
+        }
+        serde_cbor::Value::Tag(_, v) => from_cbor(*v),
+        _ => error("Unsupported cbor value"),
+    }
+}
+
+#[signature(
+from,
+can_block = true,
+output = Unknown,
+short = "Parse cbor format",
+long = "If the input contains more than one value, the values are returned as a list, or as a table if they are all maps with the same keys. Tags are ignored.",
+example = "cbor:from ./cache.cbor")]
+struct From {
+    #[unnamed()]
+    #[description("source. If unspecified, will read from io.")]
+    files: Files,
+}
+
+fn from(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: From = From::parse(context.arguments, &context.printer)?;
+    let mut reader = BufReader::new(cfg.files.reader(context.input)?);
+    let mut buf = Vec::new();
+    to_crush_error(reader.read_to_end(&mut buf))?;
+
+    let mut values = serde_cbor::Deserializer::from_slice(&buf)
+        .into_iter::<serde_cbor::Value>()
+        .map(|v| from_cbor(to_crush_error(v)?))
+        .collect::<CrushResult<Vec<Value>>>()?;
+    let crush_value = match values.len() {
+        1 => values.remove(0),
+        _ => list(values),
+    };
+    context.output.send(crush_value)
+}
+
+fn to_cbor(value: Value) -> CrushResult<serde_cbor::Value> {
+    match value.materialize() {
+        Value::File(s) =>
+            Ok(serde_cbor::Value::Text(mandate(s.to_str(), "Invalid filename")?.to_string())),
+
+        Value::String(s) => Ok(serde_cbor::Value::Text(s.to_string())),
+
+        Value::Integer(i) =>
+            Ok(serde_cbor::Value::Integer(i)),
+
+        Value::List(l) =>
+            Ok(serde_cbor::Value::Array(
+                l.dump().drain(..)
+                    .map(to_cbor)
+                    .collect::<CrushResult<Vec<_>>>()?)),
+
+        Value::Table(t) => {
+            let types = t.types().to_vec();
+            let structs = t.rows()
+                .iter()
+                .map(|r| r.clone().into_struct(&types))
+                .map(|s| to_cbor(Value::Struct(s)))
+                .collect::<CrushResult<Vec<_>>>()?;
+            Ok(serde_cbor::Value::Array(structs))
+        }
+
+        Value::Bool(b) => Ok(serde_cbor::Value::Bool(b)),
+
+        Value::Float(f) => Ok(serde_cbor::Value::Float(f)),
+
+        Value::Struct(s) =>
+            Ok(serde_cbor::Value::Map(
+                s.local_elements()
+                    .into_iter()
+                    .map(|(k, v)| Ok((serde_cbor::Value::Text(k), to_cbor(v)?)))
+                    .collect::<CrushResult<BTreeMap<_, _>>>()?)),
+
+        Value::Dict(d) =>
+            Ok(serde_cbor::Value::Map(
+                d.elements()
+                    .into_iter()
+                    .map(|(k, v)| Ok((to_cbor(k)?, to_cbor(v)?)))
+                    .collect::<CrushResult<BTreeMap<_, _>>>()?)),
+
+        Value::Duration(d) => Ok(match seconds(&d) {
+            Seconds::Whole(s) => serde_cbor::Value::Integer(s as i128),
+            Seconds::Fractional(s) => serde_cbor::Value::Float(s),
+        }),
+
+        Value::Time(t) => Ok(serde_cbor::Value::Text(t.to_rfc3339())),
+
+        Value::Binary(b) => Ok(serde_cbor::Value::Bytes(b)),
+
+        Value::Empty() => Ok(serde_cbor::Value::Null),
+
+        v => error(format!(
+            "Unsupported data type {}",
+            v.value_type().to_string()).as_str()
+        ),
+    }
+}
+
+#[signature(
+to,
+can_block = true,
+output = Unknown,
+short = "Serialize to cbor format",
+long = "Structs and dicts become maps, which do not preserve the order of their keys. Lists and tables become arrays. If no file is specified, output is returned as a BinaryStream.",
+example = "ls | cbor:to ./listing.cbor")]
+struct To {
+    #[unnamed()]
+    #[description("destination. If unspecified, will write to io.")]
+    file: Files,
+}
+
+fn to(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: To = To::parse(context.arguments, &context.printer)?;
+    let mut writer = cfg.file.writer(context.output)?;
+    let value = to_cbor(context.input.recv()?)?;
+    to_crush_error(writer.write_all(&to_crush_error(serde_cbor::to_vec(&value))?))
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "cbor",
+        Box::new(move |env| {
+            From::declare(env)?;
+            To::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
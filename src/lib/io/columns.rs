@@ -0,0 +1,236 @@
+use std::io::{BufReader, BufRead};
+use crate::lang::{
+    execution_context::ExecutionContext,
+    table::Row,
+    value::Value,
+};
+use crate::lang::errors::{CrushResult, to_crush_error, argument_error, mandate};
+use crate::lang::files::Files;
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+use crate::lang::scope::ScopeLoader;
+use crate::lang::value::ValueType;
+use crate::lang::ordered_string_map::OrderedStringMap;
+use crate::lang::command::OutputType::Unknown;
+use crate::lib::io::csv::{INFERENCE_ROWS, columns, parse_cell};
+
+#[signature(
+from,
+can_block = true,
+output = Unknown,
+short = "Parse text aligned in columns, like the output of df or docker ps",
+long = "The first line is the header. Columns are separated by positions that are blank on the header and on the first lines of text. If whitespace is true, columns are instead separated by whitespace and the last column contains the rest of the line.",
+long = "Column names are lower cased, with any non-alphanumeric characters replaced by underscores. The type of any column that isn't declared is guessed from its values.",
+example = "df | columns:from | where {available < 1000000} | select ^filesystem ^mounted_on")]
+struct From {
+    #[unnamed()]
+    #[description("source. If unspecified, will read from io, which must be a binary or binary_stream.")]
+    files: Files,
+    #[named()]
+    #[description("name and type of columns.")]
+    columns: OrderedStringMap<ValueType>,
+    #[default(false)]
+    #[description("split on whitespace instead of finding fixed width columns.")]
+    whitespace: bool,
+    #[default(0usize)]
+    #[description("skip this many lines of input before the header.")]
+    head: usize,
+}
+
+/**
+How the lines of the input are split into cells.
+*/
+enum Layout {
+    /** The number of columns. */
+    Whitespace(usize),
+    /** The position of the first character of each column. */
+    Fixed(Vec<usize>),
+}
+
+impl Layout {
+    fn split(&self, line: &str) -> Vec<String> {
+        match self {
+            Layout::Whitespace(count) => {
+                let mut res = Vec::new();
+                let mut rest = line.trim();
+                while !rest.is_empty() && res.len() + 1 < *count {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    res.push(rest[..end].to_string());
+                    rest = rest[end..].trim_start();
+                }
+                if !rest.is_empty() {
+                    res.push(rest.to_string());
+                }
+                res.resize(*count, String::new());
+                res
+            }
+            Layout::Fixed(starts) => {
+                let line = line.chars().collect::<Vec<_>>();
+                starts.iter()
+                    .enumerate()
+                    .map(|(idx, start)| cell(
+                        &line,
+                        if idx == 0 { 0 } else { *start },
+                        starts.get(idx + 1).cloned().unwrap_or(line.len())))
+                    .collect()
+            }
+        }
+    }
+}
+
+fn is_blank(line: &[char], idx: usize) -> bool {
+    line.get(idx).map(|c| c.is_whitespace()).unwrap_or(true)
+}
+
+fn cell(line: &[char], start: usize, end: usize) -> String {
+    let end = end.min(line.len());
+    if start >= end {
+        String::new()
+    } else {
+        line[start..end].iter().collect::<String>().trim().to_string()
+    }
+}
+
+/**
+Find the name and the first position of each fixed width column. A column
+without a name belongs to the column to its left, and so does a column
+without values that is separated from it by a single space, like the last
+word of the "Mounted on" column of df.
+*/
+fn fixed_columns(header: &str, sample: &[String]) -> Vec<(String, usize)> {
+    let header = header.chars().collect::<Vec<_>>();
+    let lines = sample.iter().map(|l| l.chars().collect::<Vec<_>>()).collect::<Vec<_>>();
+    let width = lines.iter().map(|l| l.len()).max().unwrap_or(0).max(header.len());
+
+    let mut segments = Vec::new();
+    let mut start = None;
+    for idx in 0..=width {
+        let blank = is_blank(&header, idx) && lines.iter().all(|l| is_blank(l, idx));
+        match (start, blank) {
+            (None, false) => start = Some(idx),
+            (Some(s), true) => {
+                segments.push((s, idx));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    let mut columns: Vec<(String, usize, usize)> = Vec::new();
+    for (start, end) in segments {
+        let name = cell(&header, start, end);
+        let has_values = lines.iter().any(|l| !cell(l, start, end).is_empty());
+        match columns.last_mut() {
+            Some(previous) if name.is_empty() || (!has_values && start == previous.2 + 1) => {
+                previous.0 = cell(&header, previous.1, end);
+                previous.2 = end;
+            }
+            _ => columns.push((name, start, end)),
+        }
+    }
+    columns.into_iter().map(|(name, start, _)| (name, start)).collect()
+}
+
+fn column_name(name: &str, idx: usize) -> String {
+    let name = name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    if name.is_empty() {
+        format!("column{}", idx + 1)
+    } else {
+        name
+    }
+}
+
+fn from(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: From = From::parse(context.arguments, &context.printer)?;
+    let reader = BufReader::new(cfg.files.reader(context.input)?);
+    let mut lines = reader.lines()
+        .map(|line| to_crush_error(line).map(|l| l.trim_end_matches('\r').to_string()))
+        .skip(cfg.head)
+        .filter(|line| !matches!(line, Ok(l) if l.trim().is_empty()));
+
+    let header = mandate(lines.next(), "columns: Missing header")??;
+    let mut sample = Vec::new();
+    while sample.len() < INFERENCE_ROWS {
+        match lines.next() {
+            Some(line) => sample.push(line?),
+            None => break,
+        }
+    }
+
+    let (names, layout) = if cfg.whitespace {
+        let names = header.split_whitespace().map(|s| s.to_string()).collect::<Vec<_>>();
+        let count = names.len();
+        (names, Layout::Whitespace(count))
+    } else {
+        let (names, starts) = fixed_columns(&header, &sample).into_iter().unzip();
+        (names, Layout::Fixed(starts))
+    };
+    let names = names.iter()
+        .enumerate()
+        .map(|(idx, name)| column_name(name, idx))
+        .collect::<Vec<_>>();
+
+    let declared = cfg.columns;
+    for name in declared.keys() {
+        if !names.contains(name) {
+            return argument_error(format!("Unknown column {}", name).as_str());
+        }
+    }
+
+    let buffer = sample.iter().map(|l| layout.split(l)).collect::<Vec<_>>();
+    let (types, columns) = columns(&names, &declared, &buffer);
+    let output = context.output.initialize(columns.clone())?;
+
+    let rest = lines.map(|line| line.map(|l| layout.split(&l)));
+    for cells in buffer.into_iter().map(Ok).chain(rest) {
+        let row = cells?
+            .iter()
+            .zip(types.iter().zip(columns.iter()))
+            .map(|(s, (t, c))| parse_cell(s, t, c))
+            .collect::<CrushResult<Vec<Value>>>()?;
+        if output.send(Row::new(row)).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "columns",
+        Box::new(move |env| {
+            From::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_column_detection() {
+        let sample = vec![
+            "udev        812   0% /dev".to_string(),
+            "/dev/sda1  5230   2% /".to_string(),
+        ];
+        let columns = fixed_columns("Filesystem Size Use% Mounted on", &sample);
+        assert_eq!(
+            columns,
+            vec![
+                ("Filesystem".to_string(), 0),
+                ("Size".to_string(), 11),
+                ("Use%".to_string(), 16),
+                ("Mounted on".to_string(), 21),
+            ]);
+        let layout = Layout::Fixed(columns.into_iter().map(|(_, start)| start).collect());
+        assert_eq!(layout.split("tmpfs         1   1% /run/user"), vec!["tmpfs", "1", "1%", "/run/user"]);
+        assert_eq!(column_name("Use%", 2), "use");
+        assert_eq!(column_name("", 3), "column4");
+    }
+}
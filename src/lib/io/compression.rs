@@ -0,0 +1,117 @@
+use crate::lang::errors::{CrushResult, to_crush_error};
+use crate::lang::files::Files;
+use crate::lang::stream::{ValueReceiver, ValueSender};
+use crate::lang::scope::ScopeLoader;
+use crate::util::compression::Compression;
+
+pub fn decompress(compression: Compression, files: Files, input: ValueReceiver, output: ValueSender) -> CrushResult<()> {
+    let mut reader = compression.decoder(files.raw_reader(input)?)?;
+    let mut writer = Files::new().writer(output)?;
+    to_crush_error(std::io::copy(&mut reader, &mut writer))?;
+    Ok(())
+}
+
+pub fn compress(compression: Compression, level: Option<i128>, file: Files, input: ValueReceiver, output: ValueSender) -> CrushResult<()> {
+    let level = compression.level(level)?;
+    let mut reader = Files::new().raw_reader(input)?;
+    compression.encode(&mut reader, file.writer(output)?, level)
+}
+
+/**
+Create a module with the from and to commands of a compression format, and
+a declare function that puts them in a namespace with the name of the module.
+*/
+macro_rules! codec {
+    ($name:ident, $compression:ident, $from_short:tt, $from_example:tt, $to_short:tt, $to_example:tt, $level:tt) => {
+        pub mod $name {
+            use crate::lang::execution_context::ExecutionContext;
+            use crate::lang::errors::CrushResult;
+            use crate::lang::files::Files;
+            use crate::lang::scope::ScopeLoader;
+            use crate::lang::command::OutputType::Known;
+            use crate::lang::value::ValueType;
+            use crate::util::compression::Compression;
+            use signature::signature;
+            use crate::lang::argument::ArgumentHandler;
+            use super::{decompress, compress};
+
+            #[signature(
+            from,
+            can_block = true,
+            output = Known(ValueType::BinaryStream),
+            short = $from_short,
+            long = "Concatenated streams are decompressed one after the other.",
+            example = $from_example)]
+            struct From {
+                #[unnamed()]
+                #[description("source. If unspecified, will read from io.")]
+                files: Files,
+            }
+
+            fn from(context: ExecutionContext) -> CrushResult<()> {
+                let cfg: From = From::parse(context.arguments, &context.printer)?;
+                decompress(Compression::$compression, cfg.files, context.input, context.output)
+            }
+
+            #[signature(
+            to,
+            can_block = true,
+            output = Known(ValueType::BinaryStream),
+            short = $to_short,
+            long = "If no file is specified, output is returned as a BinaryStream.",
+            example = $to_example)]
+            struct To {
+                #[unnamed()]
+                #[description("destination. If unspecified, will write to io.")]
+                file: Files,
+                #[description($level)]
+                level: Option<i128>,
+            }
+
+            fn to(context: ExecutionContext) -> CrushResult<()> {
+                let cfg: To = To::parse(context.arguments, &context.printer)?;
+                compress(Compression::$compression, cfg.level, cfg.file, context.input, context.output)
+            }
+
+            pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+                root.create_lazy_namespace(
+                    stringify!($name),
+                    Box::new(move |env| {
+                        From::declare(env)?;
+                        To::declare(env)?;
+                        Ok(())
+                    }))?;
+                Ok(())
+            }
+        }
+    };
+}
+
+codec!(
+    gzip, Gzip,
+    "Decompress gzip data", "gzip:from ./access.log.gz | lines:from",
+    "Compress binary data using gzip", "ls | csv:to | gzip:to ./listing.csv.gz",
+    "compression level, from 0 to 9, where the default is 6.");
+codec!(
+    zstd, Zstd,
+    "Decompress zstd data", "zstd:from ./access.log.zst | lines:from",
+    "Compress binary data using zstd", "ls | csv:to | zstd:to ./listing.csv.zst",
+    "compression level, from 1 to 22, where the default is 3.");
+codec!(
+    bzip2, Bzip2,
+    "Decompress bzip2 data", "bzip2:from ./access.log.bz2 | lines:from",
+    "Compress binary data using bzip2", "ls | csv:to | bzip2:to ./listing.csv.bz2",
+    "compression level, from 1 to 9, where the default is 6.");
+codec!(
+    xz, Xz,
+    "Decompress xz data", "xz:from ./access.log.xz | lines:from",
+    "Compress binary data using xz", "ls | csv:to | xz:to ./listing.csv.xz",
+    "compression level, from 0 to 9, where the default is 6.");
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    bzip2::declare(root)?;
+    gzip::declare(root)?;
+    xz::declare(root)?;
+    zstd::declare(root)?;
+    Ok(())
+}
@@ -4,7 +4,6 @@ use crate::{
         table::Row,
         value::Value,
     },
-    lang::errors::CrushError,
 };
 use std::{
     io::BufReader,
@@ -12,7 +11,7 @@ use std::{
 };
 
 use crate::lang::table::ColumnType;
-use crate::lang::errors::{CrushResult, to_crush_error, error};
+use crate::lang::errors::{CrushResult, to_crush_error, error, argument_error, mandate};
 
 use signature::signature;
 use crate::lang::argument::ArgumentHandler;
@@ -20,11 +19,21 @@ use crate::lang::value::ValueType;
 use crate::lang::ordered_string_map::OrderedStringMap;
 use crate::lang::files::Files;
 use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::Unknown;
+
+/**
+The number of rows that are read before deciding the type of columns that
+have not been declared.
+*/
+pub const INFERENCE_ROWS: usize = 100;
 
 #[signature(
     from,
+    can_block = true,
     example="csv:from separator=\",\" head=1 name=string age=integer nick=string",
-    short="Parse specified files as CSV files")]
+    short="Parse specified files as CSV files",
+    long="Fields may be quoted, in which case they can contain separators, quotes and newlines, as described in RFC 4180.",
+    long="If header is true, the column names are read from the first line. The type of any column that isn't declared is guessed from its values. Empty fields in such columns become empty cells, in which case the type of the column is any unless it holds strings.")]
 #[derive(Debug)]
 struct From {
     #[unnamed()]
@@ -36,72 +45,195 @@ struct From {
     #[description("column separator.")]
     #[default(',')]
     separator: char,
+    #[description("quote character.")]
+    #[default('"')]
+    quote: char,
     #[default(0usize)]
     #[description("skip this many lines of inpit from the beginning.")]
     head: usize,
     #[description("trim this character from start and end of every value.")]
     trim: Option<char>,
+    #[description("read the column names from the first line.")]
+    #[default(false)]
+    header: bool,
+}
+
+fn byte(c: char, name: &str) -> CrushResult<u8> {
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        argument_error(format!("The {} must be an ASCII character", name).as_str())
+    }
+}
+
+fn infer_type(records: &[Vec<String>], idx: usize) -> ValueType {
+    let values = records.iter()
+        .map(|r| r[idx].as_str())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>();
+    if values.is_empty() {
+        return ValueType::String;
+    }
+    for t in &[ValueType::Integer, ValueType::Float, ValueType::Bool] {
+        if values.iter().all(|s| t.parse(s).is_ok()) {
+            return t.clone();
+        }
+    }
+    ValueType::String
+}
+
+/**
+The columns of a table read from text, along with the types to parse their
+fields as. The type of a column that isn't declared is guessed from the
+buffered records. Empty fields in such a column become empty cells, so unless
+it holds strings, its type is any.
+*/
+pub fn columns(
+    names: &[String],
+    declared: &OrderedStringMap<ValueType>,
+    records: &[Vec<String>],
+) -> (Vec<ValueType>, Vec<ColumnType>) {
+    names.iter()
+        .enumerate()
+        .map(|(idx, name)| match declared.get(name) {
+            Some(t) => (t.clone(), ColumnType::new(name, t.clone())),
+            None => {
+                let t = infer_type(records, idx);
+                let nullable = t != ValueType::String && records.iter().any(|r| r[idx].is_empty());
+                (t.clone(), ColumnType::new(name, if nullable { ValueType::Any } else { t }))
+            }
+        })
+        .unzip()
+}
+
+pub fn parse_cell(s: &str, cell_type: &ValueType, column: &ColumnType) -> CrushResult<Value> {
+    if s.is_empty() && column.cell_type == ValueType::Any {
+        Ok(Value::Empty())
+    } else {
+        cell_type.parse(s)
+    }
+}
+
+fn check_len(record: Vec<String>, len: usize) -> CrushResult<Vec<String>> {
+    if record.len() != len {
+        error("csv: Wrong number of columns in CSV file")
+    } else {
+        Ok(record)
+    }
 }
 
 fn from(context: ExecutionContext) -> CrushResult<()> {
     let cfg: From = From::parse(context.arguments, &context.printer)?;
-    let columns = cfg.columns.iter().map(|(k, v)| ColumnType::new(k, v.clone())).collect::<Vec<_>>();
-    let output = context.output.initialize(columns.clone())?;
-
     let mut reader = BufReader::new(cfg.files.reader(context.input)?);
 
-    let separator = cfg.separator;
-    let trim = cfg.trim;
-    let skip = cfg.head;
-
     let mut line = String::new();
-    let mut skipped = 0usize;
-    loop {
-        line.clear();
+    for _ in 0..cfg.head {
         to_crush_error(reader.read_line(&mut line))?;
-        if line.is_empty() {
-            break;
-        }
-        if skipped < skip {
-            skipped += 1;
-            continue;
-        }
-        let line_without_newline = &line[0..line.len() - 1];
-        let mut split: Vec<&str> = line_without_newline
-            .split(separator)
+    }
+
+    let trim = cfg.trim;
+    let mut records = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(byte(cfg.separator, "separator")?)
+        .quote(byte(cfg.quote, "quote")?)
+        .from_reader(reader)
+        .into_records()
+        .map(|record| to_crush_error(record).map(|record| record.iter()
             .map(|s| trim
                 .map(|c| s.trim_matches(c))
-                .unwrap_or(s))
-            .collect();
+                .unwrap_or(s)
+                .to_string())
+            .collect::<Vec<String>>()));
 
-        if split.len() != columns.len() {
-            return error("csv: Wrong number of columns in CSV file");
+    let declared = cfg.columns;
+    let names = if cfg.header {
+        mandate(records.next(), "csv: Missing header")??
+    } else if declared.is_empty() {
+        return argument_error("Expected either a header or a list of columns");
+    } else {
+        declared.keys().cloned().collect()
+    };
+    for name in declared.keys() {
+        if !names.contains(name) {
+            return argument_error(format!("Unknown column {}", name).as_str());
         }
+    }
 
-        if let Some(trim) = trim {
-            split = split.iter().map(|s| s.trim_matches(trim)).collect();
+    let mut buffer = Vec::new();
+    if names.iter().any(|name| !declared.contains_key(name)) {
+        while buffer.len() < INFERENCE_ROWS {
+            match records.next() {
+                Some(record) => buffer.push(check_len(record?, names.len())?),
+                None => break,
+            }
         }
+    }
 
-        match split.iter()
-            .zip(columns.iter())
-            .map({ |(s, t)| t.cell_type.parse(*s) })
-            .collect::<Result<Vec<Value>, CrushError>>() {
-            Ok(cells) => {
-                let _ = output.send(Row::new(cells));
-            }
-            Err(err) => {
-                return Err(err);
-            }
+    let (types, columns) = columns(&names, &declared, &buffer);
+    let output = context.output.initialize(columns.clone())?;
+
+    for record in buffer.into_iter().map(Ok).chain(records) {
+        let cells = check_len(record?, columns.len())?
+            .iter()
+            .zip(types.iter().zip(columns.iter()))
+            .map(|(s, (t, c))| parse_cell(s, t, c))
+            .collect::<CrushResult<Vec<Value>>>()?;
+        if output.send(Row::new(cells)).is_err() {
+            break;
         }
     }
     Ok(())
 }
 
+#[signature(
+    to,
+    can_block = true,
+    output = Unknown,
+    short = "Serialize to csv format",
+    long = "Fields are quoted when needed, as described in RFC 4180. Empty cells are written as empty fields.",
+    example = "ls | csv:to ./files.csv")]
+struct To {
+    #[unnamed()]
+    #[description("destination. If unspecified, will write to io.")]
+    file: Files,
+    #[description("column separator.")]
+    #[default(',')]
+    separator: char,
+    #[description("write the column names on the first line.")]
+    #[default(true)]
+    header: bool,
+}
+
+fn to_csv(value: &Value) -> String {
+    match value {
+        Value::Empty() => String::new(),
+        v => v.to_string(),
+    }
+}
+
+fn to(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: To = To::parse(context.arguments, &context.printer)?;
+    let mut input = mandate(context.input.recv()?.stream(), "Expected a table or a table stream")?;
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(byte(cfg.separator, "separator")?)
+        .from_writer(cfg.file.writer(context.output)?);
+
+    if cfg.header {
+        to_crush_error(writer.write_record(input.types().iter().map(|t| t.name.as_str())))?;
+    }
+    while let Ok(row) = input.read() {
+        to_crush_error(writer.write_record(row.cells().iter().map(to_csv)))?;
+    }
+    to_crush_error(writer.flush())
+}
+
 pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
     root.create_lazy_namespace(
         "csv",
         Box::new(move |env| {
             From::declare(env)?;
+            To::declare(env)?;
             Ok(())
         }))?;
     Ok(())
@@ -0,0 +1,60 @@
+use crate::lang::{list::List, table::Table, table::ColumnType, value::Value, value::ValueType};
+use chrono::Duration;
+use std::collections::HashSet;
+
+/**
+The element type of a list or dict holding the specified values, i.e. the
+type of the values if they all have the same one, and any otherwise.
+*/
+pub fn common_type(values: &[Value]) -> ValueType {
+    let types: HashSet<ValueType> = values.iter().map(|v| v.value_type()).collect();
+    if types.len() == 1 {
+        types.into_iter().next().unwrap()
+    } else {
+        ValueType::Any
+    }
+}
+
+/**
+The value of a sequence in a document format like YAML, MessagePack or CBOR.
+A sequence of structs that all have the same members becomes a table, an empty
+sequence becomes an empty value and any other sequence becomes a list.
+*/
+pub fn list(values: Vec<Value>) -> Value {
+    if values.is_empty() {
+        return Value::Empty();
+    }
+    let signatures: HashSet<Option<Vec<ColumnType>>> = values.iter()
+        .map(|v| match v {
+            Value::Struct(s) => Some(s.local_signature()),
+            _ => None,
+        })
+        .collect();
+    match signatures.into_iter().collect::<Vec<_>>().as_slice() {
+        [Some(signature)] => Value::Table(Table::new(
+            signature.clone(),
+            values.into_iter()
+                .filter_map(|v| match v {
+                    Value::Struct(s) => Some(s.to_row()),
+                    _ => None,
+                })
+                .collect())),
+        _ => Value::List(List::new(common_type(&values), values)),
+    }
+}
+
+/**
+A duration written as a number of seconds, which is a float unless the
+duration is a whole number of seconds.
+*/
+pub enum Seconds {
+    Whole(i64),
+    Fractional(f64),
+}
+
+pub fn seconds(d: &Duration) -> Seconds {
+    match d.num_nanoseconds() {
+        Some(n) if n % 1_000_000_000 != 0 => Seconds::Fractional(n as f64 / 1e9),
+        _ => Seconds::Whole(d.num_seconds()),
+    }
+}
@@ -0,0 +1,82 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::value::Value;
+use std::io::{BufReader, Read};
+
+use crate::lang::errors::{CrushResult, to_crush_error};
+use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::Unknown;
+use crate::lang::files::Files;
+use crate::lib::io::xml::{element, run_query};
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+use scraper::{Html, Node};
+use ego_tree::NodeRef;
+
+fn from_node(node: NodeRef<Node>) -> Option<Value> {
+    match node.value() {
+        Node::Element(e) => Some(element(
+            e.name(),
+            e.attrs()
+                .map(|(name, value)| (name.to_string(), Value::string(value)))
+                .collect(),
+            node.children().filter_map(from_node).collect())),
+        Node::Text(t) if !t.trim().is_empty() => Some(Value::string(t)),
+        _ => None,
+    }
+}
+
+fn parse(s: &str) -> CrushResult<Value> {
+    let document = Html::parse_document(s);
+    Ok(from_node(*document.root_element()).unwrap())
+}
+
+#[signature(
+from,
+can_block = true,
+output = Unknown,
+short = "Parse html format",
+long = "Malformed documents are parsed the same way a web browser would. The result has the same format as the output of xml:from, and can be queried using html:query. Text consisting only of whitespace and comments are skipped.",
+example = "(http \"https://example.com\"):body | html:from")]
+struct From {
+    #[unnamed()]
+    files: Files,
+}
+
+fn from(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: From = From::parse(context.arguments, &context.printer)?;
+    let mut reader = BufReader::new(cfg.files.reader(context.input)?);
+    let mut s = String::new();
+    to_crush_error(reader.read_to_string(&mut s))?;
+    context.output.send(parse(&s)?)
+}
+
+#[signature(
+query,
+can_block = true,
+short = "Find the elements of an html document that match a CSS selector",
+long = "The input can be a file, a binary stream or a document parsed by html:from. The output has one row per matching element, with its tag, attributes and text content.",
+long = "Any selector supported by web browsers can be used, including several selectors separated by commas. Tag names are matched case-insensitively.",
+example = "(http \"https://example.com\"):body | html:query \"div > p a[href]\"")]
+struct Query {
+    #[description("the CSS selector to match elements against.")]
+    selector: String,
+    #[unnamed()]
+    #[description("source. If unspecified, will read from io.")]
+    files: Files,
+}
+
+fn query(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Query = Query::parse(context.arguments.clone(), &context.printer)?;
+    run_query(&cfg.selector, "http://www.w3.org/1999/xhtml", cfg.files, context, parse)
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "html",
+        Box::new(move |env| {
+            From::declare(env)?;
+            Query::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
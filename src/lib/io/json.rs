@@ -20,8 +20,14 @@ use crate::lang::command::OutputType::Unknown;
 use crate::lang::files::Files;
 use signature::signature;
 use crate::lang::argument::ArgumentHandler;
-
-fn from_json(json_value: &serde_json::Value) -> CrushResult<Value> {
+use crate::lib::io::jsonl::RowSender;
+use crate::lib::io::csv::INFERENCE_ROWS;
+use serde::de::{Deserializer, Error, SeqAccess, Visitor};
+use std::fmt;
+use std::str::FromStr;
+use num_bigint::BigInt;
+
+pub fn from_json(json_value: &serde_json::Value) -> CrushResult<Value> {
     match json_value {
         serde_json::Value::Null => Ok(Value::Empty()),
         serde_json::Value::Bool(b) => Ok(Value::Bool(*b)),
@@ -30,8 +36,10 @@ fn from_json(json_value: &serde_json::Value) -> CrushResult<Value> {
                 Ok(Value::Integer(f.as_u64().expect("") as i128))
             } else if f.is_i64() {
                 Ok(Value::Integer(f.as_i64().expect("") as i128))
+            } else if let Ok(i) = BigInt::from_str(&f.to_string()) {
+                Ok(Value::big_integer(i))
             } else {
-                Ok(Value::Float(f.as_f64().ok_or(CrushError { kind: InvalidData, message: "Not a valid number".to_string() })?))
+                Ok(Value::Float(f.as_f64().ok_or(CrushError::new(InvalidData, "Not a valid number"))?))
             }
         }
         serde_json::Value::String(s) => Ok(Value::string(s.as_str())),
@@ -86,15 +94,20 @@ fn from_json(json_value: &serde_json::Value) -> CrushResult<Value> {
     }
 }
 
-fn to_json(value: Value) -> CrushResult<serde_json::Value> {
+pub fn to_json(value: Value) -> CrushResult<serde_json::Value> {
     match value.materialize() {
         Value::File(s) =>
             Ok(serde_json::Value::from(mandate(s.to_str(), "Invalid filename")?)),
 
         Value::String(s) => Ok(serde_json::Value::from(s)),
 
-        Value::Integer(i) =>
-            Ok(serde_json::Value::from(to_crush_error(i64::try_from(i))?)),
+        Value::Integer(i) => match i64::try_from(i) {
+            Ok(i) => Ok(serde_json::Value::from(i)),
+            Err(_) => Ok(serde_json::Value::Number(to_crush_error(serde_json::Number::from_str(&i.to_string()))?)),
+        },
+
+        Value::BigInteger(i) =>
+            Ok(serde_json::Value::Number(to_crush_error(serde_json::Number::from_str(&i.to_string()))?)),
 
         Value::List(l) =>
             Ok(serde_json::Value::Array(
@@ -130,6 +143,8 @@ fn to_json(value: Value) -> CrushResult<serde_json::Value> {
 
         Value::Binary(b) => Ok(serde_json::Value::from(b)),
 
+        Value::Empty() => Ok(serde_json::Value::Null),
+
         Value::BinaryStream(_) => panic!("Impossible"),
 
         Value::TableStream(_) => panic!("Impossible"),
@@ -146,15 +161,62 @@ from,
 can_block = true,
 output = Unknown,
 short = "Parse json format",
+long = "If stream is true, the input must be an array. Its elements are parsed one at a time and returned as a table stream, like jsonl:from does.",
 example = "(http \"https://jsonplaceholder.typicode.com/todos/3\"):body | json:from")]
 struct From {
     #[unnamed()]
     files: Files,
+    #[default(false)]
+    #[description("return the elements of an array as a table stream as they are parsed.")]
+    stream: bool,
+}
+
+/**
+Sends the elements of an array as they are parsed, without reading the whole
+array into memory.
+*/
+struct ElementVisitor<'a> {
+    sender: &'a mut RowSender,
+    closed: &'a mut bool,
+}
+
+impl<'de, 'a> Visitor<'de> for ElementVisitor<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(element) = seq.next_element::<serde_json::Value>()? {
+            match self.sender.send(&element) {
+                Ok(true) => {}
+                Ok(false) => {
+                    *self.closed = true;
+                    return Ok(());
+                }
+                Err(e) => return Err(A::Error::custom(e.message)),
+            }
+        }
+        Ok(())
+    }
 }
 
 pub fn from(mut context: ExecutionContext) -> CrushResult<()> {
     let cfg: From = From::parse(context.arguments, &context.printer)?;
     let reader = BufReader::new(cfg.files.reader(context.input)?);
+    if cfg.stream {
+        let mut sender = RowSender::new(context.output, INFERENCE_ROWS);
+        let mut closed = false;
+        let res = serde_json::Deserializer::from_reader(reader)
+            .deserialize_seq(ElementVisitor { sender: &mut sender, closed: &mut closed });
+        // The rest of the array is never read if nobody wants the rows
+        if closed {
+            return Ok(());
+        }
+        to_crush_error(res)?;
+        return sender.finish();
+    }
     let serde_value = to_crush_error(serde_json::from_reader(reader))?;
     let crush_value = from_json(&serde_value)?;
     context.output.send(crush_value)
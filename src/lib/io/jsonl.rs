@@ -0,0 +1,231 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::{table::Row, value::ValueType, value::Value};
+use std::io::{BufReader, BufRead, Write};
+use crate::lang::errors::{CrushResult, to_crush_error, error};
+use std::collections::HashSet;
+use crate::lang::table::ColumnType;
+use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::{Known, Unknown};
+use crate::lang::files::Files;
+use crate::lang::stream::{OutputStream, ValueSender};
+use crate::lib::io::json::{from_json, to_json};
+use crate::lib::io::csv::INFERENCE_ROWS;
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+
+/**
+The type that the values of a column are converted to, which is decided from
+the values among the first ones that are neither missing nor null.
+*/
+fn column_type(values: &[Value], name: &str) -> ValueType {
+    let types = values.iter()
+        .flat_map(|v| match v {
+            Value::Struct(s) => s.get(name),
+            v => Some(v.clone()),
+        })
+        .map(|v| v.value_type())
+        .filter(|t| *t != ValueType::Empty)
+        .collect::<HashSet<_>>();
+    if types.len() == 2 && types.contains(&ValueType::Integer) && types.contains(&ValueType::Float) {
+        return ValueType::Float;
+    }
+    match (types.len(), types.into_iter().next()) {
+        (1, Some(ValueType::List(_))) | (1, Some(ValueType::Dict(_, _))) | (1, Some(ValueType::Table(_))) =>
+            ValueType::Any,
+        (1, Some(t)) => t,
+        _ => ValueType::Any,
+    }
+}
+
+/**
+The columns of a stream of values. Objects become one column per member,
+any other values a single column named value.
+*/
+fn columns(values: &[Value]) -> CrushResult<Vec<ColumnType>> {
+    match values.first() {
+        None => Ok(vec![]),
+        Some(Value::Struct(_)) => {
+            let mut names: Vec<String> = Vec::new();
+            for value in values {
+                match value {
+                    Value::Struct(s) =>
+                        for (name, _) in s.local_elements() {
+                            if !names.contains(&name) {
+                                names.push(name);
+                            }
+                        },
+                    _ => return error("Expected every value to be an object"),
+                }
+            }
+            Ok(names.iter()
+                .map(|name| ColumnType::new(name, column_type(values, name)))
+                .collect())
+        }
+        Some(_) => Ok(vec![ColumnType::new("value", column_type(values, "value"))]),
+    }
+}
+
+/**
+Any member may be missing or null in a value that comes after the ones the
+columns were decided from, and its type may differ from theirs, so values are
+only converted where that doesn't lose anything and otherwise kept as they
+are. For the same reason, the columns of the stream have the type any.
+*/
+fn cell(value: Value, column: &ColumnType) -> Value {
+    match (value, &column.cell_type) {
+        (Value::Integer(i), ValueType::Float) => Value::Float(i as f64),
+        (value, _) => value,
+    }
+}
+
+fn row(value: Value, columns: &[ColumnType]) -> CrushResult<Row> {
+    match value {
+        Value::Struct(s) => Ok(Row::new(
+            columns.iter()
+                .map(|c| cell(s.get(&c.name).unwrap_or(Value::Empty()), c))
+                .collect())),
+        value if columns.len() == 1 && columns[0].name == "value" =>
+            Ok(Row::new(vec![cell(value, &columns[0])])),
+        _ => error("Expected every value to be an object"),
+    }
+}
+
+/**
+Sends JSON values as the rows of a table stream. The first values are
+buffered in order to decide the columns of the stream. Object members that
+don't appear in any of those values are ignored.
+*/
+pub struct RowSender {
+    sender: ValueSender,
+    sample: usize,
+    buffer: Vec<Value>,
+    output: Option<(Vec<ColumnType>, OutputStream)>,
+}
+
+impl RowSender {
+    pub fn new(sender: ValueSender, sample: usize) -> RowSender {
+        RowSender { sender, sample: sample.max(1), buffer: Vec::new(), output: None }
+    }
+
+    /**
+    Send the value, returning false if nobody is reading the stream anymore.
+    */
+    pub fn send(&mut self, value: &serde_json::Value) -> CrushResult<bool> {
+        let value = from_json(value)?;
+        match &self.output {
+            None => {
+                self.buffer.push(value);
+                if self.buffer.len() >= self.sample {
+                    self.flush()
+                } else {
+                    Ok(true)
+                }
+            }
+            Some((columns, output)) => Ok(output.send(row(value, columns)?).is_ok()),
+        }
+    }
+
+    fn flush(&mut self) -> CrushResult<bool> {
+        let columns = columns(&self.buffer)?;
+        let output = self.sender.initialize(
+            columns.iter().map(|c| ColumnType::new(&c.name, ValueType::Any)).collect())?;
+        let mut open = true;
+        for value in self.buffer.drain(..) {
+            if output.send(row(value, &columns)?).is_err() {
+                open = false;
+                break;
+            }
+        }
+        self.output = Some((columns, output));
+        Ok(open)
+    }
+
+    pub fn finish(mut self) -> CrushResult<()> {
+        if self.output.is_none() {
+            self.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[signature(
+from,
+can_block = true,
+output = Unknown,
+short = "Parse JSON lines format, i.e. one JSON value per line",
+long = "The values are returned as a table stream, with one column per member of the objects among the first values. If the values are not objects, the stream has a single column named value. Since any value may lack a member or have a member of another type than the values before it, the columns have the type any, but integers are converted to floats in columns that hold floats.",
+example = "jsonl:from ./app.log | where {level == \"error\"}")]
+struct From {
+    #[unnamed()]
+    #[description("source. If unspecified, will read from io.")]
+    files: Files,
+    #[default(INFERENCE_ROWS)]
+    #[description("the number of values used to decide the columns and their types.")]
+    sample: usize,
+}
+
+fn from(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: From = From::parse(context.arguments, &context.printer)?;
+    let reader = BufReader::new(cfg.files.reader(context.input)?);
+    let mut sender = RowSender::new(context.output, cfg.sample);
+    for (idx, line) in reader.lines().enumerate() {
+        let line = to_crush_error(line)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value = match serde_json::from_str(&line) {
+            Ok(value) => value,
+            Err(e) => return error(format!("Invalid JSON on line {}: {}", idx + 1, e).as_str()),
+        };
+        if !sender.send(&value)? {
+            return Ok(());
+        }
+    }
+    sender.finish()
+}
+
+#[signature(
+to,
+can_block = true,
+output = Known(ValueType::BinaryStream),
+short = "Serialize to JSON lines format",
+long = "Every row of a table or every element of a list is written as a JSON value on a line of its own. Rows are written as they arrive.",
+example = "ps | jsonl:to ./processes.jsonl")]
+struct To {
+    #[unnamed()]
+    #[description("destination. If unspecified, will write to io.")]
+    file: Files,
+}
+
+fn to(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: To = To::parse(context.arguments, &context.printer)?;
+    let mut writer = cfg.file.writer(context.output)?;
+    let value = context.input.recv()?;
+    match value {
+        Value::List(l) =>
+            for element in l.dump() {
+                to_crush_error(writeln!(writer, "{}", to_json(element)?))?;
+            },
+        value => match value.stream() {
+            Some(mut stream) => {
+                let types = stream.types().to_vec();
+                while let Ok(row) = stream.read() {
+                    to_crush_error(writeln!(writer, "{}", to_json(Value::Struct(row.into_struct(&types)))?))?;
+                }
+            }
+            None => to_crush_error(writeln!(writer, "{}", to_json(value)?))?,
+        },
+    }
+    Ok(())
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "jsonl",
+        Box::new(move |env| {
+            From::declare(env)?;
+            To::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
@@ -8,15 +8,30 @@ use crate::lang::argument::ArgumentHandler;
 use crate::lang::command::OutputType::{Known};
 use signature::signature;
 
+mod archive;
+mod avro;
 mod bin;
+mod cbor;
+mod columns;
+mod compression;
 mod csv;
+mod document;
+mod html;
 mod http;
 mod json;
+mod jsonl;
 mod lines;
+mod msgpack;
+mod pbuf;
 mod pup;
 mod split;
+mod sqlite;
+mod tar;
 mod toml;
 mod words;
+mod xml;
+mod yaml;
+mod zipfile;
 
 pub fn val(mut context: ExecutionContext) -> CrushResult<()> {
     context.arguments.check_len(1)?;
@@ -74,14 +89,27 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
     let e = root.create_lazy_namespace(
         "io",
         Box::new(move |env| {
+            avro::declare(env)?;
             bin::declare(env)?;
+            cbor::declare(env)?;
+            columns::declare(env)?;
+            compression::declare(env)?;
             csv::declare(env)?;
+            html::declare(env)?;
             pup::declare(env)?;
             toml::declare(env)?;
             json::declare(env)?;
+            jsonl::declare(env)?;
             lines::declare(env)?;
+            msgpack::declare(env)?;
+            pbuf::declare(env)?;
             split::declare(env)?;
+            sqlite::declare(env)?;
+            tar::declare(env)?;
             words::declare(env)?;
+            xml::declare(env)?;
+            yaml::declare(env)?;
+            zipfile::declare(env)?;
 
             http::Http::declare(env)?;
             Echo::declare(env)?;
@@ -0,0 +1,189 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::value::Value;
+use std::io::{BufReader, Read, Write, Cursor};
+
+use crate::lang::{r#struct::Struct, dict::Dict};
+use crate::lang::errors::{CrushResult, to_crush_error, error, mandate};
+use std::convert::TryFrom;
+use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::Unknown;
+use crate::lang::files::Files;
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+use crate::lib::io::document::{common_type, list, seconds, Seconds};
+
+fn from_msgpack(msgpack_value: rmpv::Value) -> CrushResult<Value> {
+    match msgpack_value {
+        rmpv::Value::Nil => Ok(Value::Empty()),
+        rmpv::Value::Boolean(b) => Ok(Value::Bool(b)),
+        rmpv::Value::Integer(i) => match (i.as_i64(), i.as_u64()) {
+            (Some(i), _) => Ok(Value::Integer(i as i128)),
+            (None, Some(i)) => Ok(Value::Integer(i as i128)),
+            _ => error("Not a valid number"),
+        },
+        rmpv::Value::F32(f) => Ok(Value::Float(f as f64)),
+        rmpv::Value::F64(f) => Ok(Value::Float(f)),
+        rmpv::Value::String(s) => Ok(Value::string(mandate(s.as_str(), "Invalid utf-8 string")?)),
+        rmpv::Value::Binary(b) => Ok(Value::Binary(b)),
+        rmpv::Value::Array(arr) => Ok(list(
+            arr.into_iter()
+                .map(from_msgpack)
+                .collect::<CrushResult<Vec<Value>>>()?)),
+        rmpv::Value::Map(m) => {
+            if m.iter().all(|(k, _)| k.is_str()) {
+                Ok(Value::Struct(
+                    Struct::new(
+                        m
+                            .into_iter()
+                            .map(|(k, v)| Ok((k.as_str().unwrap().to_string(), from_msgpack(v)?)))
+                            .collect::<CrushResult<Vec<(String, Value)>>>()?,
+                        None,
+                    )))
+            } else {
+                let (keys, values): (Vec<_>, Vec<_>) = m.into_iter().unzip();
+                let keys = keys.into_iter().map(from_msgpack).collect::<CrushResult<Vec<Value>>>()?;
+                let values = values.into_iter().map(from_msgpack).collect::<CrushResult<Vec<Value>>>()?;
+                let key_type = common_type(&keys);
+                if !key_type.is_hashable() {
+                    return error("Unsupported map key type");
+                }
+                let dict = Dict::new(key_type, common_type(&values));
+                for (k, v) in keys.into_iter().zip(values) {
+                    dict.insert(k, v)?;
+                }
+                Ok(Value::Dict(dict))
+            }
+        }
+        rmpv::Value::Ext(_, b) => Ok(Value::Binary(b)),
+    }
+}
+
+#[signature(
+from,
+can_block = true,
+output = Unknown,
+short = "Parse msgpack format",
+long = "If the input contains more than one value, the values are returned as a list, or as a table if they are all maps with the same keys.",
+example = "msgpack:from ./cache.msgpack")]
+struct From {
+    #[unnamed()]
+    #[description("source. If unspecified, will read from io.")]
+    files: Files,
+}
+
+fn from(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: From = From::parse(context.arguments, &context.printer)?;
+    let mut reader = BufReader::new(cfg.files.reader(context.input)?);
+    let mut buf = Vec::new();
+    to_crush_error(reader.read_to_end(&mut buf))?;
+
+    let mut cursor = Cursor::new(buf.as_slice());
+    let mut values = Vec::new();
+    while (cursor.position() as usize) < buf.len() {
+        values.push(from_msgpack(to_crush_error(rmpv::decode::read_value(&mut cursor))?)?);
+    }
+    let crush_value = match values.len() {
+        1 => values.remove(0),
+        _ => list(values),
+    };
+    context.output.send(crush_value)
+}
+
+fn to_msgpack(value: Value) -> CrushResult<rmpv::Value> {
+    match value.materialize() {
+        Value::File(s) =>
+            Ok(rmpv::Value::from(mandate(s.to_str(), "Invalid filename")?)),
+
+        Value::String(s) => Ok(rmpv::Value::from(s)),
+
+        Value::Integer(i) =>
+            match (i64::try_from(i), u64::try_from(i)) {
+                (Ok(i), _) => Ok(rmpv::Value::from(i)),
+                (_, Ok(i)) => Ok(rmpv::Value::from(i)),
+                (Err(e), _) => to_crush_error(Err(e)),
+            },
+
+        Value::List(l) =>
+            Ok(rmpv::Value::Array(
+                l.dump().drain(..)
+                    .map(to_msgpack)
+                    .collect::<CrushResult<Vec<_>>>()?)),
+
+        Value::Table(t) => {
+            let types = t.types().to_vec();
+            let structs = t.rows()
+                .iter()
+                .map(|r| r.clone().into_struct(&types))
+                .map(|s| to_msgpack(Value::Struct(s)))
+                .collect::<CrushResult<Vec<_>>>()?;
+            Ok(rmpv::Value::Array(structs))
+        }
+
+        Value::Bool(b) => Ok(rmpv::Value::from(b)),
+
+        Value::Float(f) => Ok(rmpv::Value::from(f)),
+
+        Value::Struct(s) =>
+            Ok(rmpv::Value::Map(
+                s.local_elements()
+                    .into_iter()
+                    .map(|(k, v)| Ok((rmpv::Value::from(k.as_str()), to_msgpack(v)?)))
+                    .collect::<CrushResult<Vec<_>>>()?)),
+
+        Value::Dict(d) =>
+            Ok(rmpv::Value::Map(
+                d.elements()
+                    .into_iter()
+                    .map(|(k, v)| Ok((to_msgpack(k)?, to_msgpack(v)?)))
+                    .collect::<CrushResult<Vec<_>>>()?)),
+
+        Value::Duration(d) => Ok(match seconds(&d) {
+            Seconds::Whole(s) => rmpv::Value::from(s),
+            Seconds::Fractional(s) => rmpv::Value::from(s),
+        }),
+
+        Value::Time(t) => Ok(rmpv::Value::from(t.to_rfc3339())),
+
+        Value::Binary(b) => Ok(rmpv::Value::Binary(b)),
+
+        Value::Empty() => Ok(rmpv::Value::Nil),
+
+        v => error(format!(
+            "Unsupported data type {}",
+            v.value_type().to_string()).as_str()
+        ),
+    }
+}
+
+#[signature(
+to,
+can_block = true,
+output = Unknown,
+short = "Serialize to msgpack format",
+long = "Structs and dicts become maps, lists and tables become arrays. If no file is specified, output is returned as a BinaryStream.",
+example = "ls | msgpack:to ./listing.msgpack")]
+struct To {
+    #[unnamed()]
+    #[description("destination. If unspecified, will write to io.")]
+    file: Files,
+}
+
+fn to(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: To = To::parse(context.arguments, &context.printer)?;
+    let mut writer = cfg.file.writer(context.output)?;
+    let value = to_msgpack(context.input.recv()?)?;
+    let mut buf = Vec::new();
+    to_crush_error(rmpv::encode::write_value(&mut buf, &value))?;
+    to_crush_error(writer.write_all(&buf))
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "msgpack",
+        Box::new(move |env| {
+            From::declare(env)?;
+            To::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
@@ -0,0 +1,232 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::value::{Value, ValueType};
+use std::io::{BufReader, Read, Write};
+
+use crate::lang::{r#struct::Struct, list::List, dict::Dict};
+use crate::lang::errors::{CrushResult, to_crush_error, error, argument_error, mandate};
+use std::convert::TryFrom;
+use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::Unknown;
+use crate::lang::files::Files;
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+use protobuf::MessageDyn;
+use protobuf::reflect::{
+    FileDescriptor, MessageDescriptor, FieldDescriptor, ReflectFieldRef, ReflectValueRef,
+    ReflectValueBox, RuntimeFieldType, RuntimeType,
+};
+use std::path::Path;
+
+/**
+Parse the specified .proto file and look up a message type in it by its
+fully qualified name. Imports are resolved relative to the directory of
+the schema.
+*/
+fn message_descriptor(schema: Files, name: &str) -> CrushResult<MessageDescriptor> {
+    let mut files = schema.into_vec();
+    if files.len() != 1 {
+        return argument_error("Expected exactly one schema file");
+    }
+    let file = files.remove(0);
+    let include = file.parent().unwrap_or_else(|| Path::new("."));
+    let parsed = match protobuf_parse::Parser::new()
+        .pure()
+        .include(include)
+        .input(&file)
+        .parse_and_typecheck() {
+        Ok(parsed) => parsed,
+        Err(e) => return error(format!("pbuf: Invalid schema: {}", e).as_str()),
+    };
+    let descriptors = to_crush_error(FileDescriptor::new_dynamic_fds(parsed.file_descriptors, &[]))?;
+    let full_name = format!(".{}", name.trim_start_matches('.'));
+    mandate(
+        descriptors.iter().find_map(|d| d.message_by_full_name(&full_name)),
+        format!("pbuf: Unknown message type {}", name).as_str())
+}
+
+fn value_type(t: &RuntimeType) -> ValueType {
+    match t {
+        RuntimeType::I32 | RuntimeType::I64 | RuntimeType::U32 | RuntimeType::U64 => ValueType::Integer,
+        RuntimeType::F32 | RuntimeType::F64 => ValueType::Float,
+        RuntimeType::Bool => ValueType::Bool,
+        RuntimeType::String | RuntimeType::Enum(_) => ValueType::String,
+        RuntimeType::VecU8 => ValueType::Binary,
+        RuntimeType::Message(_) => ValueType::Struct,
+    }
+}
+
+fn from_value(value: ReflectValueRef) -> CrushResult<Value> {
+    Ok(match value {
+        ReflectValueRef::U32(v) => Value::Integer(v as i128),
+        ReflectValueRef::U64(v) => Value::Integer(v as i128),
+        ReflectValueRef::I32(v) => Value::Integer(v as i128),
+        ReflectValueRef::I64(v) => Value::Integer(v as i128),
+        ReflectValueRef::F32(v) => Value::Float(v as f64),
+        ReflectValueRef::F64(v) => Value::Float(v),
+        ReflectValueRef::Bool(v) => Value::Bool(v),
+        ReflectValueRef::String(v) => Value::string(v),
+        ReflectValueRef::Bytes(v) => Value::Binary(v.to_vec()),
+        ReflectValueRef::Enum(d, n) =>
+            Value::string(d.value_by_number(n)
+                .map(|v| v.name().to_string())
+                .unwrap_or_else(|| n.to_string())
+                .as_str()),
+        ReflectValueRef::Message(m) => from_message(&*m)?,
+    })
+}
+
+fn from_field(field: &FieldDescriptor, message: &dyn MessageDyn) -> CrushResult<Value> {
+    match (field.get_reflect(message), field.runtime_field_type()) {
+        (ReflectFieldRef::Optional(o), t) => match (o.value(), t) {
+            (Some(v), _) => from_value(v),
+            (None, RuntimeFieldType::Singular(RuntimeType::Message(_))) => Ok(Value::Empty()),
+            (None, _) => from_value(field.get_singular_field_or_default(message)),
+        },
+        (ReflectFieldRef::Repeated(r), RuntimeFieldType::Repeated(t)) =>
+            Ok(Value::List(List::new(
+                value_type(&t),
+                r.into_iter().map(from_value).collect::<CrushResult<Vec<_>>>()?))),
+        (ReflectFieldRef::Map(m), RuntimeFieldType::Map(key_type, value_type_)) => {
+            let dict = Dict::new(value_type(&key_type), value_type(&value_type_));
+            for (k, v) in &m {
+                dict.insert(from_value(k)?, from_value(v)?)?;
+            }
+            Ok(Value::Dict(dict))
+        }
+        _ => error("pbuf: Inconsistent field type"),
+    }
+}
+
+fn from_message(message: &dyn MessageDyn) -> CrushResult<Value> {
+    let descriptor = message.descriptor_dyn();
+    Ok(Value::Struct(Struct::new(
+        descriptor.fields()
+            .map(|f| Ok((f.name().to_string(), from_field(&f, message)?)))
+            .collect::<CrushResult<Vec<_>>>()?,
+        None,
+    )))
+}
+
+#[signature(
+from,
+can_block = true,
+output = Unknown,
+short = "Parse protobuf format",
+long = "The message type is looked up in the specified .proto file. Messages become structs, repeated fields become lists, maps become dicts and enums become the name of their value.",
+example = "pbuf:from ./person.bin schema=./person.proto type=\"example.Person\"")]
+struct From {
+    #[unnamed()]
+    #[description("source. If unspecified, will read from io.")]
+    files: Files,
+    #[description(".proto file containing the message definition.")]
+    schema: Files,
+    #[description("fully qualified name of the message type.")]
+    r#type: String,
+}
+
+fn from(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: From = From::parse(context.arguments, &context.printer)?;
+    let descriptor = message_descriptor(cfg.schema, &cfg.r#type)?;
+    let mut reader = BufReader::new(cfg.files.reader(context.input)?);
+    let mut buf = Vec::new();
+    to_crush_error(reader.read_to_end(&mut buf))?;
+    let message = to_crush_error(descriptor.parse_from_bytes(&buf))?;
+    context.output.send(from_message(&*message)?)
+}
+
+fn to_value(t: &RuntimeType, value: Value, name: &str) -> CrushResult<ReflectValueBox> {
+    Ok(match (t, value) {
+        (RuntimeType::I32, Value::Integer(i)) => ReflectValueBox::I32(to_crush_error(i32::try_from(i))?),
+        (RuntimeType::I64, Value::Integer(i)) => ReflectValueBox::I64(to_crush_error(i64::try_from(i))?),
+        (RuntimeType::U32, Value::Integer(i)) => ReflectValueBox::U32(to_crush_error(u32::try_from(i))?),
+        (RuntimeType::U64, Value::Integer(i)) => ReflectValueBox::U64(to_crush_error(u64::try_from(i))?),
+        (RuntimeType::F32, Value::Integer(i)) => ReflectValueBox::F32(i as f32),
+        (RuntimeType::F64, Value::Integer(i)) => ReflectValueBox::F64(i as f64),
+        (RuntimeType::F32, Value::Float(f)) => ReflectValueBox::F32(f as f32),
+        (RuntimeType::F64, Value::Float(f)) => ReflectValueBox::F64(f),
+        (RuntimeType::Bool, Value::Bool(b)) => ReflectValueBox::Bool(b),
+        (RuntimeType::String, Value::String(s)) => ReflectValueBox::String(s.to_string()),
+        (RuntimeType::VecU8, Value::Binary(b)) => ReflectValueBox::Bytes(b),
+        (RuntimeType::VecU8, Value::String(s)) => ReflectValueBox::Bytes(s.as_bytes().to_vec()),
+        (RuntimeType::Enum(d), Value::String(s)) =>
+            ReflectValueBox::Enum(
+                d.clone(),
+                mandate(
+                    d.value_by_name(&s),
+                    format!("pbuf: Unknown value {} for field {}", s, name).as_str())?.value()),
+        (RuntimeType::Enum(d), Value::Integer(i)) => ReflectValueBox::Enum(d.clone(), to_crush_error(i32::try_from(i))?),
+        (RuntimeType::Message(d), Value::Struct(s)) => ReflectValueBox::Message(to_message(d, &s)?),
+        (_, v) => return error(format!(
+            "pbuf: Invalid value of type {} for field {}",
+            v.value_type().to_string(), name).as_str()),
+    })
+}
+
+fn to_message(descriptor: &MessageDescriptor, s: &Struct) -> CrushResult<Box<dyn MessageDyn>> {
+    let mut message = descriptor.new_instance();
+    for (name, value) in s.local_elements() {
+        let field = mandate(
+            descriptor.field_by_name(&name),
+            format!("pbuf: Unknown field {} in message {}", name, descriptor.full_name()).as_str())?;
+        match (field.runtime_field_type(), value.materialize()) {
+            (_, Value::Empty()) => {}
+            (RuntimeFieldType::Singular(t), value) =>
+                field.set_singular_field(&mut *message, to_value(&t, value, &name)?),
+            (RuntimeFieldType::Repeated(t), Value::List(l)) =>
+                for value in l.dump() {
+                    let value = to_value(&t, value, &name)?;
+                    field.mut_repeated(&mut *message).push(value);
+                },
+            (RuntimeFieldType::Map(key_type, value_type), Value::Dict(d)) =>
+                for (k, v) in d.elements() {
+                    let k = to_value(&key_type, k, &name)?;
+                    let v = to_value(&value_type, v, &name)?;
+                    field.mut_map(&mut *message).insert(k, v);
+                },
+            (_, v) => return error(format!(
+                "pbuf: Invalid value of type {} for field {}",
+                v.value_type().to_string(), name).as_str()),
+        }
+    }
+    Ok(message)
+}
+
+#[signature(
+to,
+can_block = true,
+output = Unknown,
+short = "Serialize to protobuf format",
+long = "The input must be a struct, with members matching the fields of the message type. Lists are used for repeated fields, dicts for maps and strings for enums. Empty members are left unset.",
+example = "$person | pbuf:to ./person.bin schema=./person.proto type=\"example.Person\"")]
+struct To {
+    #[unnamed()]
+    #[description("destination. If unspecified, will write to io.")]
+    file: Files,
+    #[description(".proto file containing the message definition.")]
+    schema: Files,
+    #[description("fully qualified name of the message type.")]
+    r#type: String,
+}
+
+fn to(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: To = To::parse(context.arguments, &context.printer)?;
+    let descriptor = message_descriptor(cfg.schema, &cfg.r#type)?;
+    let message = match context.input.recv()?.materialize() {
+        Value::Struct(s) => to_message(&descriptor, &s)?,
+        _ => return argument_error("Expected a struct"),
+    };
+    let bytes = to_crush_error(message.write_to_bytes_dyn())?;
+    let mut writer = cfg.file.writer(context.output)?;
+    to_crush_error(writer.write_all(&bytes))
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "pbuf",
+        Box::new(move |env| {
+            From::declare(env)?;
+            To::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
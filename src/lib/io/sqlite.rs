@@ -0,0 +1,283 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::errors::{CrushResult, to_crush_error, argument_error, data_error, mandate};
+use crate::lang::files::Files;
+use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::{Known, Unknown};
+use crate::lang::value::{Value, ValueType};
+use crate::lang::table::{ColumnType, Row};
+use crate::lang::ordered_string_map::OrderedStringMap;
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone, Utc};
+use rusqlite::{Connection, OpenFlags};
+use rusqlite::types::ValueRef;
+use crate::lang::printer::Printer;
+use std::collections::{HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+/**
+The database file, which is either passed by name or as the first of the
+unnamed arguments. Files can't be the target of a mix of unnamed arguments of
+different types, so the unnamed arguments are taken apart here.
+*/
+fn database_file(mut database: Files, arguments: &mut VecDeque<Value>, printer: &Printer) -> CrushResult<PathBuf> {
+    if !database.had_entries() {
+        database.expand(mandate(arguments.pop_front(), "Missing database file")?, printer)?;
+    }
+    let mut files = database.into_vec();
+    if files.len() != 1 {
+        return argument_error("Expected exactly one database file");
+    }
+    Ok(files.remove(0))
+}
+
+/**
+The Crush type of a column with the specified declared type, using the same
+rules as SQLite uses to decide the affinity of a column. Columns without a
+declared type, e.g. expressions, may contain anything.
+*/
+fn column_type(declared: Option<&str>) -> ValueType {
+    let declared = match declared {
+        None => return ValueType::Any,
+        Some(d) => d.to_uppercase(),
+    };
+    if declared.contains("INT") {
+        ValueType::Integer
+    } else if declared.contains("CHAR") || declared.contains("CLOB") || declared.contains("TEXT") {
+        ValueType::String
+    } else if declared.contains("BLOB") || declared.is_empty() {
+        ValueType::Binary
+    } else if declared.contains("REAL") || declared.contains("FLOA") || declared.contains("DOUB") {
+        ValueType::Float
+    } else if declared.contains("BOOL") {
+        ValueType::Bool
+    } else if declared.contains("DATE") || declared.contains("TIME") {
+        ValueType::Time
+    } else if declared.contains("DURATION") {
+        ValueType::Duration
+    } else {
+        ValueType::Any
+    }
+}
+
+/**
+The declared type of a column created from a Crush column. Durations are
+stored as nanoseconds and times as RFC 3339 strings.
+*/
+fn declared_type(column: &ColumnType) -> CrushResult<&'static str> {
+    match column.cell_type {
+        ValueType::Integer => Ok("INTEGER"),
+        ValueType::Float => Ok("REAL"),
+        ValueType::String | ValueType::File => Ok("TEXT"),
+        ValueType::Binary => Ok("BLOB"),
+        ValueType::Bool => Ok("BOOLEAN"),
+        ValueType::Time => Ok("TIMESTAMP"),
+        ValueType::Duration => Ok("DURATION"),
+        ValueType::Any => Ok(""),
+        _ => argument_error(
+            format!(
+                "Unsupported type {} of column {}",
+                column.cell_type.to_string(), column.name).as_str()),
+    }
+}
+
+fn string_argument(arguments: &mut VecDeque<Value>, name: &str) -> CrushResult<String> {
+    match arguments.pop_front() {
+        Some(Value::String(s)) => Ok(s),
+        Some(v) => argument_error(
+            format!("Expected the {} to be of type string, was of type {}", name, v.value_type().to_string()).as_str()),
+        None => argument_error(format!("Missing {}", name).as_str()),
+    }
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn parse_time(s: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(s).ok()
+        .map(|t| t.with_timezone(&Local))
+        .or_else(|| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok()
+            .map(|t| Utc.from_utc_datetime(&t).with_timezone(&Local)))
+}
+
+fn from_sqlite(value: ValueRef, column: &ColumnType) -> CrushResult<Value> {
+    let res = match (value, &column.cell_type) {
+        (ValueRef::Null, _) => Some(Value::Empty()),
+        (ValueRef::Integer(i), ValueType::Bool) => Some(Value::Bool(i != 0)),
+        (ValueRef::Integer(i), ValueType::Time) => Some(Value::Time(Local.timestamp(i, 0))),
+        (ValueRef::Integer(i), ValueType::Duration) => Some(Value::Duration(Duration::nanoseconds(i))),
+        (ValueRef::Integer(i), ValueType::Float) => Some(Value::Float(i as f64)),
+        (ValueRef::Integer(i), ValueType::Integer) | (ValueRef::Integer(i), ValueType::Any) =>
+            Some(Value::Integer(i as i128)),
+        (ValueRef::Real(f), ValueType::Float) | (ValueRef::Real(f), ValueType::Any) =>
+            Some(Value::Float(f)),
+        (ValueRef::Text(s), ValueType::Time) => parse_time(&String::from_utf8_lossy(s)).map(Value::Time),
+        (ValueRef::Text(s), ValueType::String) | (ValueRef::Text(s), ValueType::Any) =>
+            Some(Value::string(&String::from_utf8_lossy(s))),
+        (ValueRef::Blob(b), ValueType::Binary) | (ValueRef::Blob(b), ValueType::Any) =>
+            Some(Value::Binary(b.to_vec())),
+        _ => None,
+    };
+    mandate(
+        res,
+        format!(
+            "Value in column {} does not match the declared type {}",
+            column.name, column.cell_type.to_string()).as_str())
+}
+
+fn to_sqlite(value: Value) -> CrushResult<rusqlite::types::Value> {
+    Ok(match value {
+        Value::Empty() => rusqlite::types::Value::Null,
+        Value::Integer(i) => rusqlite::types::Value::Integer(to_crush_error(i64::try_from(i))?),
+        Value::Float(f) => rusqlite::types::Value::Real(f),
+        Value::Bool(b) => rusqlite::types::Value::Integer(b as i64),
+        Value::String(s) => rusqlite::types::Value::Text(s),
+        Value::File(f) => rusqlite::types::Value::Text(mandate(f.to_str(), "Invalid filename")?.to_string()),
+        Value::Binary(b) => rusqlite::types::Value::Blob(b),
+        Value::Time(t) => rusqlite::types::Value::Text(t.to_rfc3339()),
+        Value::Duration(d) => rusqlite::types::Value::Integer(mandate(d.num_nanoseconds(), "Duration is too long")?),
+        v => return data_error(
+            format!("Unsupported data type {}", v.value_type().to_string()).as_str()),
+    })
+}
+
+#[signature(
+query,
+can_block = true,
+output = Unknown,
+short = "Run a query against an SQLite database",
+long = "The rows are returned as a table stream. Values are converted according to the declared types of the columns in the database. Since any column may contain NULL, which becomes an empty cell, the columns of the output have the type any.",
+long = "Unnamed arguments after the query are bound to its parameters in order, and named arguments to the parameters of the query with the same name. Every parameter of the query must be given a value.",
+example = "sqlite:query ./state.db \"select * from jobs where owner = :owner\" owner=\"fox\"")]
+struct Query {
+    #[description("the database file, unless it is the first unnamed argument.")]
+    database: Files,
+    #[unnamed()]
+    #[description("the database file unless passed by name, the SQL query to run and the values of its positional parameters.")]
+    values: Vec<Value>,
+    #[named()]
+    #[description("values of the query parameters.")]
+    parameters: OrderedStringMap<Value>,
+}
+
+fn query(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Query = Query::parse(context.arguments, &context.printer)?;
+    let mut arguments = VecDeque::from(cfg.values);
+    let connection = to_crush_error(Connection::open_with_flags(
+        database_file(cfg.database, &mut arguments, &context.printer)?,
+        OpenFlags::SQLITE_OPEN_READ_ONLY))?;
+    let query = string_argument(&mut arguments, "query")?;
+    let mut statement = to_crush_error(connection.prepare(&query))?;
+
+    if arguments.len() > statement.parameter_count() {
+        return argument_error(format!(
+            "The query has {} parameters, but {} values were given",
+            statement.parameter_count(), arguments.len()).as_str());
+    }
+    let mut bound = HashSet::new();
+    for (idx, value) in arguments.into_iter().enumerate() {
+        to_crush_error(statement.raw_bind_parameter(idx + 1, to_sqlite(value)?))?;
+        bound.insert(idx + 1);
+    }
+    for (name, value) in cfg.parameters {
+        let idx = [":", "@", "$"].iter()
+            .map(|prefix| statement.parameter_index(&format!("{}{}", prefix, name)))
+            .collect::<Result<Vec<_>, _>>();
+        let idx = mandate(
+            to_crush_error(idx)?.into_iter().flatten().next(),
+            format!("Unknown query parameter {}", name).as_str())?;
+        to_crush_error(statement.raw_bind_parameter(idx, to_sqlite(value)?))?;
+        bound.insert(idx);
+    }
+    if let Some(idx) = (1..=statement.parameter_count()).find(|idx| !bound.contains(idx)) {
+        return argument_error(format!(
+            "No value given for query parameter {}",
+            statement.parameter_name(idx).map(|n| n.to_string()).unwrap_or_else(|| idx.to_string())).as_str());
+    }
+
+    let types = statement.columns()
+        .iter()
+        .map(|c| ColumnType::new(c.name(), column_type(c.decl_type())))
+        .collect::<Vec<_>>();
+    let output = context.output.initialize(
+        types.iter().map(|c| ColumnType::new(&c.name, ValueType::Any)).collect())?;
+    let mut rows = statement.raw_query();
+    while let Some(row) = to_crush_error(rows.next())? {
+        let cells = types.iter()
+            .enumerate()
+            .map(|(idx, column)| from_sqlite(to_crush_error(row.get_ref(idx))?, column))
+            .collect::<CrushResult<Vec<_>>>()?;
+        if output.send(Row::new(cells)).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[signature(
+insert,
+can_block = true,
+output = Known(ValueType::Empty),
+short = "Insert the rows of the input into a table of an SQLite database",
+long = "The database and the table are created if they don't exist, using the column names and types of the input.",
+example = "ps | select ^pid ^name | sqlite:insert ./state.db \"processes\"")]
+struct Insert {
+    #[description("the database file, unless it is the first unnamed argument.")]
+    database: Files,
+    #[unnamed()]
+    #[description("the database file unless passed by name, and the table to insert into.")]
+    values: Vec<Value>,
+}
+
+fn insert(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Insert = Insert::parse(context.arguments, &context.printer)?;
+    let mut arguments = VecDeque::from(cfg.values);
+    let database = database_file(cfg.database, &mut arguments, &context.printer)?;
+    let table = string_argument(&mut arguments, "table name")?;
+    if !arguments.is_empty() {
+        return argument_error("Too many arguments");
+    }
+    let mut input = mandate(context.input.recv()?.stream(), "Expected a stream")?;
+    let types = input.types().to_vec();
+    if types.is_empty() {
+        return argument_error("Expected a stream with at least one column");
+    }
+
+    let mut connection = to_crush_error(Connection::open(database))?;
+    let transaction = to_crush_error(connection.transaction())?;
+    let table = quote_identifier(&table);
+    let columns = types.iter()
+        .map(|c| Ok(format!("{} {}", quote_identifier(&c.name), declared_type(c)?)))
+        .collect::<CrushResult<Vec<_>>>()?;
+    to_crush_error(transaction.execute(
+        &format!("CREATE TABLE IF NOT EXISTS {} ({})", table, columns.join(", ")),
+        []))?;
+    {
+        let mut statement = to_crush_error(transaction.prepare(&format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            types.iter().map(|c| quote_identifier(&c.name)).collect::<Vec<_>>().join(", "),
+            (1..=types.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", "))))?;
+        while let Ok(row) = input.read() {
+            for (idx, cell) in row.into_vec().into_iter().enumerate() {
+                to_crush_error(statement.raw_bind_parameter(idx + 1, to_sqlite(cell)?))?;
+            }
+            to_crush_error(statement.raw_execute())?;
+        }
+    }
+    to_crush_error(transaction.commit())?;
+    context.output.send(Value::Empty())
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "sqlite",
+        Box::new(move |env| {
+            Query::declare(env)?;
+            Insert::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
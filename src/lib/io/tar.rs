@@ -0,0 +1,148 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::errors::{CrushResult, to_crush_error, error};
+use crate::lang::files::Files;
+use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::Known;
+use crate::lang::value::{Value, ValueType};
+use crate::lang::table::Row;
+use crate::lang::binary::BinaryReader;
+use crate::lib::io::archive::{Source, OUTPUT_TYPE, destination, entry_name};
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::{Local, TimeZone};
+use tar::{Archive, Builder, EntryType};
+
+fn entry_type(t: EntryType) -> &'static str {
+    if t.is_file() {
+        "file"
+    } else if t.is_dir() {
+        "directory"
+    } else if t.is_symlink() {
+        "symlink"
+    } else if t.is_hard_link() {
+        "hardlink"
+    } else {
+        "other"
+    }
+}
+
+fn copy_entry(source: &Source, name: &Path, writer: &mut dyn Write) -> CrushResult<()> {
+    let mut archive = Archive::new(source.reader()?);
+    for entry in to_crush_error(archive.entries())? {
+        let mut entry = to_crush_error(entry)?;
+        if to_crush_error(entry.path())? == name {
+            to_crush_error(std::io::copy(&mut entry, writer))?;
+            return Ok(());
+        }
+    }
+    error(format!("tar: Missing entry {}", name.to_str().unwrap_or("?")).as_str())
+}
+
+#[signature(
+list,
+can_block = true,
+output = Known(ValueType::TableStream(OUTPUT_TYPE.clone())),
+short = "List the entries of a tar archive",
+long = "The data column contains a binary stream with the contents of each file. Compressed archive files are decompressed.",
+example = "tar:list ./src.tar.gz | where {type == \"file\"} | select ^file ^size")]
+struct List {
+    #[unnamed()]
+    #[description("the archive. If unspecified, will read from io.")]
+    files: Files,
+}
+
+fn list(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: List = List::parse(context.arguments, &context.printer)?;
+    let source = Source::new(cfg.files, context.input)?;
+    let output = context.output.initialize(OUTPUT_TYPE.clone())?;
+    let mut archive = Archive::new(source.reader()?);
+    for entry in to_crush_error(archive.entries())? {
+        let entry = to_crush_error(entry)?;
+        let header = entry.header();
+        let name = to_crush_error(entry.path())?.components().collect::<PathBuf>();
+        let data = if header.entry_type().is_file() {
+            let source = source.clone();
+            let name = name.clone();
+            Value::BinaryStream(<dyn BinaryReader>::lazy(move |w| copy_entry(&source, &name, w)))
+        } else {
+            Value::Empty()
+        };
+        let row = Row::new(vec![
+            Value::Integer(i128::from(to_crush_error(header.size())?)),
+            Value::Time(Local.timestamp(to_crush_error(header.mtime())? as i64, 0)),
+            Value::string(entry_type(header.entry_type())),
+            Value::File(name),
+            data,
+        ]);
+        if output.send(row).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[signature(
+extract,
+can_block = true,
+output = Known(ValueType::Empty),
+short = "Extract all entries of a tar archive",
+long = "Compressed archive files are decompressed.",
+example = "tar:extract ./src.tar.gz directory=./build")]
+struct Extract {
+    #[unnamed()]
+    #[description("the archive. If unspecified, will read from io.")]
+    files: Files,
+    #[description("the directory to extract into. Defaults to the current working directory.")]
+    directory: Files,
+}
+
+fn extract(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Extract = Extract::parse(context.arguments, &context.printer)?;
+    let source = Source::new(cfg.files, context.input)?;
+    to_crush_error(Archive::new(source.reader()?).unpack(destination(cfg.directory)?))?;
+    context.output.send(Value::Empty())
+}
+
+#[signature(
+create,
+can_block = true,
+output = Known(ValueType::BinaryStream),
+short = "Create a tar archive",
+long = "Directories are added recursively. If no destination is specified, the archive is returned as a BinaryStream.",
+example = "tar:create src Cargo.toml | gzip:to ./src.tar.gz")]
+struct Create {
+    #[unnamed()]
+    #[description("the files and directories to add to the archive.")]
+    files: Files,
+    #[description("the file to write the archive to.")]
+    destination: Files,
+}
+
+fn create(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Create = Create::parse(context.arguments, &context.printer)?;
+    let mut builder = Builder::new(cfg.destination.writer(context.output)?);
+    for path in cfg.files.into_vec() {
+        let name = entry_name(&path)?;
+        if path.is_dir() {
+            to_crush_error(builder.append_dir_all(&name, &path))?;
+        } else {
+            to_crush_error(builder.append_path_with_name(&path, &name))?;
+        }
+    }
+    to_crush_error(builder.into_inner())?;
+    Ok(())
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "tar",
+        Box::new(move |env| {
+            List::declare(env)?;
+            Extract::declare(env)?;
+            Create::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
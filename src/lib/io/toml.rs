@@ -116,8 +116,12 @@ fn to_toml(value: Value) -> CrushResult<toml::Value> {
 
         Value::String(s) => Ok(toml::Value::from(s.as_ref())),
 
-        Value::Integer(i) =>
-            Ok(toml::Value::from(to_crush_error(i64::try_from(i))?)),
+        Value::Integer(i) => match i64::try_from(i) {
+            Ok(i) => Ok(toml::Value::from(i)),
+            Err(_) => error(format!("Integer {} is too large for TOML", i).as_str()),
+        },
+
+        Value::BigInteger(i) => error(format!("Integer {} is too large for TOML", i).as_str()),
 
         Value::List(l) =>
             Ok(toml::Value::Array(
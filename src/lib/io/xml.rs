@@ -0,0 +1,326 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::value::{Value, ValueType};
+use std::io::{BufReader, Read, Write};
+
+use crate::lang::{r#struct::Struct, list::List, table::Row, table::ColumnType};
+use crate::lang::errors::{CrushResult, to_crush_error, error, argument_error, mandate};
+use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::Unknown;
+use crate::lang::files::Files;
+use crate::lang::stream::ValueReceiver;
+use crate::lang::binary::BinaryReader;
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+use ego_tree::NodeId;
+use html5ever::{Attribute, LocalName, Namespace, QualName};
+use html5ever::tree_builder::{ElementFlags, NodeOrText, TreeSink};
+use scraper::{Html, Selector, StrTendril};
+use std::collections::HashMap;
+
+/**
+Create the value representing an element of an XML or HTML document. Children
+are either elements or strings.
+*/
+pub fn element(tag: &str, attributes: Vec<(String, Value)>, children: Vec<Value>) -> Value {
+    Value::Struct(Struct::new(
+        vec![
+            ("tag".to_string(), Value::string(tag)),
+            ("attributes".to_string(), Value::Struct(Struct::new(attributes, None))),
+            ("children".to_string(), Value::List(List::new(ValueType::Any, children))),
+        ],
+        None))
+}
+
+/**
+Read the document to parse from the specified files, or from the input. If the
+input already is a parsed document, it is returned as is.
+*/
+pub fn document(files: Files, input: ValueReceiver, parse: fn(&str) -> CrushResult<Value>) -> CrushResult<Value> {
+    let reader = if files.had_entries() {
+        files.reader(input)?
+    } else {
+        match input.recv()? {
+            Value::Struct(s) => return Ok(Value::Struct(s)),
+            Value::BinaryStream(b) => b,
+            Value::Binary(b) => <dyn BinaryReader>::vec(&b),
+            _ => return argument_error("Expected either a file to read, binary pipe io or a parsed document"),
+        }
+    };
+    let mut s = String::new();
+    to_crush_error(BufReader::new(reader).read_to_string(&mut s))?;
+    parse(&s)
+}
+
+fn from_node(node: roxmltree::Node) -> Option<Value> {
+    if node.is_element() {
+        Some(element(
+            node.tag_name().name(),
+            node.attributes()
+                .iter()
+                .map(|a| (a.name().to_string(), Value::string(a.value())))
+                .collect(),
+            node.children().filter_map(from_node).collect()))
+    } else if node.is_text() {
+        node.text()
+            .filter(|t| !t.trim().is_empty())
+            .map(Value::string)
+    } else {
+        None
+    }
+}
+
+fn parse(s: &str) -> CrushResult<Value> {
+    let doc = to_crush_error(roxmltree::Document::parse(s))?;
+    mandate(from_node(doc.root_element()), "Missing root element")
+}
+
+#[signature(
+from,
+can_block = true,
+output = Unknown,
+short = "Parse xml format",
+long = "Every element is turned into a struct with the members tag, attributes and children. The children are elements and strings. Namespaces are dropped, so tags and attribute names are only the local part of the name. Text consisting only of whitespace, comments and processing instructions are skipped.",
+example = "xml:from pom.xml")]
+struct From {
+    #[unnamed()]
+    files: Files,
+}
+
+fn from(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: From = From::parse(context.arguments, &context.printer)?;
+    let mut reader = BufReader::new(cfg.files.reader(context.input)?);
+    let mut s = String::new();
+    to_crush_error(reader.read_to_string(&mut s))?;
+    context.output.send(parse(&s)?)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn member(s: &Struct, name: &str) -> CrushResult<Value> {
+    mandate(s.get(name), format!("Expected an element, missing member {}", name).as_str())
+}
+
+fn to_xml(value: Value, out: &mut String) -> CrushResult<()> {
+    match value {
+        Value::String(s) => out.push_str(&escape(&s)),
+        Value::Struct(s) => {
+            let tag = member(&s, "tag")?.to_string();
+            out.push('<');
+            out.push_str(&tag);
+            if let Value::Struct(attributes) = member(&s, "attributes")? {
+                for (name, value) in attributes.local_elements() {
+                    out.push_str(&format!(" {}=\"{}\"", name, escape(&value.to_string())));
+                }
+            }
+            match member(&s, "children")? {
+                Value::List(children) if children.len() > 0 => {
+                    out.push('>');
+                    for child in children.dump() {
+                        to_xml(child, out)?;
+                    }
+                    out.push_str(&format!("</{}>", tag));
+                }
+                _ => out.push_str("/>"),
+            }
+        }
+        v => return error(format!("Unsupported data type {}", v.value_type().to_string()).as_str()),
+    }
+    Ok(())
+}
+
+#[signature(
+to,
+can_block = true,
+output = Unknown,
+short = "Serialize to xml format",
+long = "The input must be an element, as created by xml:from or html:from.",
+example = "xml:from pom.xml | xml:to ./copy.xml")]
+struct To {
+    #[unnamed()]
+    file: Files,
+}
+
+fn to(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: To = To::parse(context.arguments, &context.printer)?;
+    let mut writer = cfg.file.writer(context.output)?;
+    let mut out = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string();
+    to_xml(context.input.recv()?, &mut out)?;
+    out.push('\n');
+    to_crush_error(writer.write_all(out.as_bytes()))?;
+    Ok(())
+}
+
+fn children(element: &Struct) -> Vec<Value> {
+    match element.get("children") {
+        Some(Value::List(l)) => l.dump(),
+        _ => vec![],
+    }
+}
+
+fn text(element: &Struct, out: &mut String) {
+    for child in children(element) {
+        match child {
+            Value::String(s) => out.push_str(&s),
+            Value::Struct(s) => text(&s, out),
+            _ => {}
+        }
+    }
+}
+
+/**
+Add an element and its children to the tree that selectors are matched
+against, remembering which struct every element node was created from.
+*/
+fn build(
+    element: &Struct,
+    namespace: &Namespace,
+    parent: &NodeId,
+    html: &mut Html,
+    elements: &mut HashMap<NodeId, Struct>) -> CrushResult<()> {
+    let attributes = match member(element, "attributes")? {
+        Value::Struct(attributes) => attributes.local_elements()
+            .into_iter()
+            .map(|(name, value)| Attribute {
+                name: QualName::new(None, Namespace::from(""), LocalName::from(name.as_str())),
+                value: value.to_string().into(),
+            })
+            .collect(),
+        _ => vec![],
+    };
+    let name = QualName::new(None, namespace.clone(), LocalName::from(member(element, "tag")?.to_string().as_str()));
+    let id = html.create_element(name, attributes, ElementFlags::default());
+    html.append(parent, NodeOrText::AppendNode(id));
+    elements.insert(id, element.clone());
+    for child in children(element) {
+        match child {
+            Value::Struct(child) => build(&child, namespace, &id, html, elements)?,
+            Value::String(s) => html.append(&id, NodeOrText::AppendText(StrTendril::from(s.as_ref()))),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn search(
+    root: &Struct,
+    selector: &Selector,
+    namespace: &Namespace,
+    output: &crate::lang::stream::OutputStream) -> CrushResult<()> {
+    let mut html = Html::new_document();
+    let mut elements = HashMap::new();
+    let document = html.get_document();
+    build(root, namespace, &document, &mut html, &mut elements)?;
+    for node in html.select(selector) {
+        let element = mandate(elements.get(&node.id()), "Unknown element")?;
+        let mut content = String::new();
+        text(element, &mut content);
+        output.send(Row::new(vec![
+            member(element, "tag")?,
+            member(element, "attributes")?,
+            Value::string(content.trim()),
+        ]))?;
+    }
+    Ok(())
+}
+
+#[signature(
+query,
+can_block = true,
+short = "Find the elements of an xml document that match a CSS selector",
+long = "The input can be a file, a binary stream or a document parsed by xml:from. The output has one row per matching element, with its tag, attributes and text content.",
+long = "Any selector supported by web browsers can be used, including several selectors separated by commas. Tag and attribute names are matched case-sensitively.",
+example = "xml:query \"project > dependencies artifactId\" pom.xml")]
+struct Query {
+    #[description("the CSS selector to match elements against.")]
+    selector: String,
+    #[unnamed()]
+    #[description("source. If unspecified, will read from io.")]
+    files: Files,
+}
+
+/**
+Find the elements matching a selector in a document, using the specified
+function to parse the document if it isn't already parsed. Elements are
+placed in the specified namespace when matching, and type selectors only
+ignore case for elements in the HTML namespace.
+*/
+pub fn run_query(
+    selector: &str,
+    namespace: &str,
+    files: Files,
+    context: ExecutionContext,
+    parse: fn(&str) -> CrushResult<Value>) -> CrushResult<()> {
+    let selector = match Selector::parse(selector) {
+        Ok(selector) => selector,
+        Err(e) => return argument_error(format!("Invalid selector {}: {}", selector, e).as_str()),
+    };
+    let document = document(files, context.input, parse)?;
+    let output = context.output.initialize(vec![
+        ColumnType::new("tag", ValueType::String),
+        ColumnType::new("attributes", ValueType::Struct),
+        ColumnType::new("text", ValueType::String),
+    ])?;
+    match document {
+        Value::Struct(root) => search(&root, &selector, &Namespace::from(namespace), &output),
+        _ => error("Expected an element"),
+    }
+}
+
+fn query(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Query = Query::parse(context.arguments.clone(), &context.printer)?;
+    run_query(&cfg.selector, "", cfg.files, context, parse)
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "xml",
+        Box::new(move |env| {
+            From::declare(env)?;
+            To::declare(env)?;
+            Query::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selector_matching() {
+        let doc = parse("<a><b class=\"x y\"><c id=\"i\" title=\"u,v\">t</c></b><c/></a>").unwrap();
+        let root = match doc {
+            Value::Struct(s) => s,
+            _ => panic!("Expected an element"),
+        };
+        let count = |s: &str| {
+            let (output, input) = crate::lang::stream::streams(vec![
+                ColumnType::new("tag", ValueType::String),
+                ColumnType::new("attributes", ValueType::Struct),
+                ColumnType::new("text", ValueType::String),
+            ]);
+            search(&root, &Selector::parse(s).unwrap(), &Namespace::from(""), &output).unwrap();
+            drop(output);
+            let mut res = 0;
+            while input.recv().is_ok() {
+                res += 1;
+            }
+            res
+        };
+        assert_eq!(count("c"), 2);
+        assert_eq!(count("b > c"), 1);
+        assert_eq!(count("a > c"), 1);
+        assert_eq!(count("a c"), 2);
+        assert_eq!(count(".x.y #i"), 1);
+        assert_eq!(count("[id=i], b"), 2);
+        assert_eq!(count("[title=\"u,v\"]"), 1);
+        assert_eq!(count("c:first-child"), 1);
+        assert_eq!(count("C"), 0);
+    }
+}
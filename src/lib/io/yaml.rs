@@ -0,0 +1,193 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::value::Value;
+use std::io::{BufReader, Read, Write};
+
+use crate::lang::{r#struct::Struct, dict::Dict};
+use crate::lang::errors::{CrushResult, to_crush_error, error, mandate};
+use std::convert::TryFrom;
+use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::Unknown;
+use crate::lang::files::Files;
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+use crate::lib::io::document::{common_type, list, seconds, Seconds};
+use yaml_rust::{Yaml, YamlLoader, YamlEmitter};
+
+fn from_yaml(yaml_value: &Yaml) -> CrushResult<Value> {
+    match yaml_value {
+        Yaml::Null => Ok(Value::Empty()),
+        Yaml::Boolean(b) => Ok(Value::Bool(*b)),
+        Yaml::Integer(i) => Ok(Value::Integer(*i as i128)),
+        Yaml::Real(_) => Ok(Value::Float(mandate(yaml_value.as_f64(), "Not a valid number")?)),
+        Yaml::String(s) => Ok(Value::string(s.as_str())),
+        Yaml::Array(arr) => Ok(list(
+            arr.iter()
+                .map(from_yaml)
+                .collect::<CrushResult<Vec<Value>>>()?)),
+        Yaml::Hash(h) => {
+            if h.keys().all(|k| matches!(k, Yaml::String(_))) {
+                Ok(Value::Struct(
+                    Struct::new(
+                        h
+                            .iter()
+                            .map(|(k, v)| Ok((k.as_str().unwrap().to_string(), from_yaml(v)?)))
+                            .collect::<CrushResult<Vec<(String, Value)>>>()?,
+                        None,
+                    )))
+            } else {
+                let keys = h.keys().map(from_yaml).collect::<CrushResult<Vec<Value>>>()?;
+                let values = h.values().map(from_yaml).collect::<CrushResult<Vec<Value>>>()?;
+                let key_type = common_type(&keys);
+                if !key_type.is_hashable() {
+                    return error("Unsupported mapping key type");
+                }
+                let dict = Dict::new(key_type, common_type(&values));
+                for (k, v) in keys.into_iter().zip(values) {
+                    dict.insert(k, v)?;
+                }
+                Ok(Value::Dict(dict))
+            }
+        }
+        Yaml::Alias(_) => error("Yaml aliases are not supported"),
+        Yaml::BadValue => error("Invalid yaml value"),
+    }
+}
+
+#[signature(
+from,
+can_block = true,
+output = Unknown,
+short = "Parse yaml format",
+long = "If the input contains more than one document, the documents are returned as a list, or as a table if they are all mappings with the same keys.",
+example = "yaml:from .travis.yml")]
+struct From {
+    #[unnamed()]
+    files: Files,
+}
+
+fn from(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: From = From::parse(context.arguments, &context.printer)?;
+    let mut reader = BufReader::new(cfg.files.reader(context.input)?);
+    let mut v = String::new();
+
+    to_crush_error(reader.read_to_string(&mut v))?;
+
+    let documents = to_crush_error(YamlLoader::load_from_str(&v))?;
+    let crush_value = match documents.len() {
+        1 => from_yaml(&documents[0])?,
+        _ => list(
+            documents.iter()
+                .map(from_yaml)
+                .collect::<CrushResult<Vec<Value>>>()?),
+    };
+    context.output.send(crush_value)
+}
+
+fn to_yaml(value: Value) -> CrushResult<Yaml> {
+    match value.materialize() {
+        Value::File(s) =>
+            Ok(Yaml::String(mandate(s.to_str(), "Invalid filename")?.to_string())),
+
+        Value::String(s) => Ok(Yaml::String(s.to_string())),
+
+        Value::Integer(i) =>
+            Ok(Yaml::Integer(to_crush_error(i64::try_from(i))?)),
+
+        Value::List(l) =>
+            Ok(Yaml::Array(
+                l.dump().drain(..)
+                    .map(to_yaml)
+                    .collect::<CrushResult<Vec<_>>>()?)),
+
+        Value::Table(t) => {
+            let types = t.types().to_vec();
+            let structs = t.rows()
+                .iter()
+                .map(|r| r.clone().into_struct(&types))
+                .map(|s| to_yaml(Value::Struct(s)))
+                .collect::<CrushResult<Vec<_>>>()?;
+            Ok(Yaml::Array(structs))
+        }
+
+        Value::Bool(b) => Ok(Yaml::Boolean(b)),
+
+        Value::Float(f) => Ok(Yaml::Real(f.to_string())),
+
+        Value::Struct(s) => {
+            let mut map = yaml_rust::yaml::Hash::new();
+            for (k, v) in s.local_elements() {
+                map.insert(Yaml::String(k.to_string()), to_yaml(v)?);
+            }
+            Ok(Yaml::Hash(map))
+        }
+
+        Value::Dict(d) => {
+            let mut map = yaml_rust::yaml::Hash::new();
+            for (k, v) in d.elements() {
+                map.insert(to_yaml(k)?, to_yaml(v)?);
+            }
+            Ok(Yaml::Hash(map))
+        }
+
+        Value::Duration(d) => Ok(match seconds(&d) {
+            Seconds::Whole(s) => Yaml::Integer(s),
+            Seconds::Fractional(s) => Yaml::Real(s.to_string()),
+        }),
+
+        Value::Time(t) => Ok(Yaml::String(t.to_rfc3339())),
+
+        Value::Binary(b) => Ok(Yaml::Array(b.iter().map(|c| Yaml::Integer(*c as i64)).collect())),
+
+        Value::Empty() => Ok(Yaml::Null),
+
+        v => error(format!(
+            "Unsupported data type {}",
+            v.value_type().to_string()).as_str()
+        ),
+    }
+}
+
+#[signature(
+to,
+can_block = true,
+output = Unknown,
+short = "Serialize to yaml format",
+long = "If no file is specified, output is returned as a BinaryStream.",
+example = "ls | yaml:to")]
+struct To {
+    #[unnamed()]
+    file: Files,
+    #[description("write each element of a list, or each row of a table, as a separate document.")]
+    #[default(false)]
+    documents: bool,
+}
+
+fn to(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: To = To::parse(context.arguments, &context.printer)?;
+    let mut writer = cfg.file.writer(context.output)?;
+    let yaml_value = to_yaml(context.input.recv()?)?;
+    let documents = match (cfg.documents, yaml_value) {
+        (true, Yaml::Array(documents)) => documents,
+        (true, _) => return error("Expected a list or a table"),
+        (false, yaml_value) => vec![yaml_value],
+    };
+
+    let mut out = String::new();
+    for document in &documents {
+        to_crush_error(YamlEmitter::new(&mut out).dump(document))?;
+        out.push('\n');
+    }
+    to_crush_error(writer.write_all(out.as_bytes()))?;
+    Ok(())
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "yaml",
+        Box::new(move |env| {
+            From::declare(env)?;
+            To::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
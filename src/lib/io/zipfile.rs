@@ -0,0 +1,165 @@
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::errors::{CrushResult, to_crush_error};
+use crate::lang::files::Files;
+use crate::lang::scope::ScopeLoader;
+use crate::lang::command::OutputType::Known;
+use crate::lang::value::{Value, ValueType};
+use crate::lang::table::Row;
+use crate::lang::binary::BinaryReader;
+use crate::lib::io::archive::{Source, OUTPUT_TYPE, destination, entry_name};
+use signature::signature;
+use crate::lang::argument::ArgumentHandler;
+use std::io::{Read, Seek, Write, Cursor};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use chrono::{Local, TimeZone};
+use zip::{ZipArchive, ZipWriter, DateTime};
+use zip::write::FileOptions;
+
+fn open(source: &Source) -> CrushResult<ZipArchive<Box<dyn ReadSeek>>> {
+    let reader: Box<dyn ReadSeek> = match source {
+        Source::File(path) => Box::from(to_crush_error(File::open(path))?),
+        Source::Memory(data) => Box::from(Cursor::new(data.clone())),
+    };
+    to_crush_error(ZipArchive::new(reader))
+}
+
+trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+fn copy_entry(source: &Source, name: &str, writer: &mut dyn Write) -> CrushResult<()> {
+    let mut archive = open(source)?;
+    let mut entry = to_crush_error(archive.by_name(name))?;
+    to_crush_error(std::io::copy(&mut entry, writer))?;
+    Ok(())
+}
+
+fn modified(t: DateTime) -> Value {
+    Local.ymd_opt(t.year() as i32, t.month() as u32, t.day() as u32)
+        .single()
+        .and_then(|d| d.and_hms_opt(t.hour() as u32, t.minute() as u32, t.second() as u32))
+        .map(Value::Time)
+        .unwrap_or(Value::Empty())
+}
+
+#[signature(
+list,
+can_block = true,
+output = Known(ValueType::TableStream(OUTPUT_TYPE.clone())),
+short = "List the entries of a zip archive",
+long = "The data column contains a binary stream with the contents of each file. If the archive is read from io, it is kept in memory.",
+example = "zipfile:list ./photos.zip | sort ^size")]
+struct List {
+    #[unnamed()]
+    #[description("the archive. If unspecified, will read from io.")]
+    files: Files,
+}
+
+fn list(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: List = List::parse(context.arguments, &context.printer)?;
+    let source = Source::new(cfg.files, context.input)?;
+    let output = context.output.initialize(OUTPUT_TYPE.clone())?;
+    let mut archive = open(&source)?;
+    for idx in 0..archive.len() {
+        let entry = to_crush_error(archive.by_index(idx))?;
+        let name = entry.name().to_string();
+        let data = if entry.is_file() {
+            let source = source.clone();
+            let name = name.clone();
+            Value::BinaryStream(<dyn BinaryReader>::lazy(move |w| copy_entry(&source, &name, w)))
+        } else {
+            Value::Empty()
+        };
+        let row = Row::new(vec![
+            Value::Integer(i128::from(entry.size())),
+            modified(entry.last_modified()),
+            Value::string(if entry.is_dir() { "directory" } else { "file" }),
+            Value::File(PathBuf::from(name.trim_end_matches('/'))),
+            data,
+        ]);
+        if output.send(row).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[signature(
+extract,
+can_block = true,
+output = Known(ValueType::Empty),
+short = "Extract all entries of a zip archive",
+example = "zipfile:extract ./photos.zip directory=./photos")]
+struct Extract {
+    #[unnamed()]
+    #[description("the archive. If unspecified, will read from io.")]
+    files: Files,
+    #[description("the directory to extract into. Defaults to the current working directory.")]
+    directory: Files,
+}
+
+fn extract(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Extract = Extract::parse(context.arguments, &context.printer)?;
+    let source = Source::new(cfg.files, context.input)?;
+    to_crush_error(open(&source)?.extract(destination(cfg.directory)?))?;
+    context.output.send(Value::Empty())
+}
+
+fn add(zip: &mut ZipWriter<Cursor<Vec<u8>>>, path: &Path, name: &Path) -> CrushResult<()> {
+    let name_str = name.to_string_lossy();
+    if path.is_dir() {
+        if !name_str.is_empty() {
+            to_crush_error(zip.add_directory(name_str, FileOptions::default()))?;
+        }
+        let mut children = to_crush_error(std::fs::read_dir(path))?
+            .map(|e| to_crush_error(e).map(|e| e.file_name()))
+            .collect::<CrushResult<Vec<_>>>()?;
+        children.sort();
+        for child in children {
+            add(zip, &path.join(&child), &name.join(&child))?;
+        }
+    } else {
+        to_crush_error(zip.start_file(name_str, FileOptions::default()))?;
+        to_crush_error(std::io::copy(&mut to_crush_error(File::open(path))?, zip))?;
+    }
+    Ok(())
+}
+
+#[signature(
+create,
+can_block = true,
+output = Known(ValueType::BinaryStream),
+short = "Create a zip archive",
+long = "Directories are added recursively. If no destination is specified, the archive is returned as a BinaryStream.",
+example = "zipfile:create src Cargo.toml destination=./src.zip")]
+struct Create {
+    #[unnamed()]
+    #[description("the files and directories to add to the archive.")]
+    files: Files,
+    #[description("the file to write the archive to.")]
+    destination: Files,
+}
+
+fn create(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Create = Create::parse(context.arguments, &context.printer)?;
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for path in cfg.files.into_vec() {
+        let name = entry_name(&path)?;
+        add(&mut zip, &path, &name)?;
+    }
+    let data = to_crush_error(zip.finish())?.into_inner();
+    to_crush_error(cfg.destination.writer(context.output)?.write_all(&data))
+}
+
+pub fn declare(root: &mut ScopeLoader) -> CrushResult<()> {
+    root.create_lazy_namespace(
+        "zipfile",
+        Box::new(move |env| {
+            List::declare(env)?;
+            Extract::declare(env)?;
+            Create::declare(env)?;
+            Ok(())
+        }))?;
+    Ok(())
+}
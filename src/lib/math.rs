@@ -6,6 +6,7 @@ use crate::lang::execution_context::ArgumentVector;
 use crate::lang::errors::argument_error;
 use crate::lang::command::OutputType::Known;
 use crate::lang::value::ValueType;
+use num_traits::ToPrimitive;
 
 macro_rules! math_fun {
     ($name:ident, $op:expr) => {
@@ -14,6 +15,7 @@ fn $name(mut context: ExecutionContext) -> CrushResult<()> {
     let x = match context.arguments.value(0)? {
         Value::Float(f) => f,
         Value::Integer(i) => i as f64,
+        Value::BigInteger(i) => i.to_f64().unwrap_or(f64::NAN),
         v => return argument_error(format!("Expected a number, got a {}", v.value_type().to_string()).as_str()),
     };
     context.output.send(Value::Float($op(x)))
@@ -28,11 +30,13 @@ fn $name(mut context: ExecutionContext) -> CrushResult<()> {
     let x = match context.arguments.value(0)? {
         Value::Float(f) => f,
         Value::Integer(i) => i as f64,
+        Value::BigInteger(i) => i.to_f64().unwrap_or(f64::NAN),
         v => return argument_error(format!("Expected a number, got a {}", v.value_type().to_string()).as_str()),
     };
     let y = match context.arguments.value(1)? {
         Value::Float(f) => f,
         Value::Integer(i) => i as f64,
+        Value::BigInteger(i) => i.to_f64().unwrap_or(f64::NAN),
         v => return argument_error(format!("Expected a number, got a {}", v.value_type().to_string()).as_str()),
     };
     context.output.send(Value::Float($op(x, y)))
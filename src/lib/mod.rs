@@ -20,54 +20,17 @@ mod random;
 mod host;
 
 use crate::{lang::scope::Scope, lang::errors::CrushResult};
-use crate::lang::execute;
-use crate::lang::stream::ValueSender;
 use crate::lang::printer::Printer;
-use std::path::Path;
-use std::fs::read_dir;
-use crate::lang::errors::to_crush_error;
+use crate::lang::stream::ValueSender;
+use crate::lib::control::module;
+use crate::lang::errors::mandate;
 
 fn declare_external(root: &Scope, printer: &Printer, output: &ValueSender) -> CrushResult<()> {
-    match read_dir("src/crushlib/") {
-        Err(_) => Ok(()),
-        Ok(dirs) => {
-            for lib in dirs {
-                match lib {
-                    Ok(entry) => {
-                        match entry.file_name().to_str() {
-                            None => {
-                                printer.error("Invalid filename encountered during library loading");
-                            }
-                            Some(name_with_extension) => {
-                                let name = name_with_extension.trim_end_matches(".crush");
-                                let s = load_external_namespace(name, &entry.path(), root, printer, output)?;
-                                if name == "lls" {
-                                    root.r#use(&s);
-                                }
-                            }
-                        }
-                    }
-                    err => printer.handle_error(to_crush_error(err)),
-                }
-            }
-            Ok(())
-        }
+    for (name, _) in module::STDLIB {
+        let source = mandate(module::stdlib(name), "Unknown module")?;
+        module::load(name, source, root, printer, output)?;
     }
-}
-
-fn load_external_namespace(name: &str, file: &Path, root: &Scope, printer: &Printer, output: &ValueSender) -> CrushResult<Scope> {
-    let local_printer = printer.clone();
-    let local_output = output.clone();
-    let local_file = file.to_path_buf();
-    root.create_lazy_namespace(name, Box::new(move |env| {
-        let tmp_env: Scope = env.create_temporary_namespace();
-        execute::file(tmp_env.clone(), &local_file, &local_printer, &local_output)?;
-        let data = tmp_env.export()?;
-        for (k, v) in data.mapping {
-            env.declare(&k, v)?;
-        }
-        Ok(())
-    }))
+    Ok(())
 }
 
 pub fn declare(root: &Scope, printer: &Printer, output: &ValueSender) -> CrushResult<()> {
@@ -18,6 +18,7 @@ use lazy_static::lazy_static;
 use signature::signature;
 use crate::lang::argument::ArgumentHandler;
 use crate::lang::command::OutputType::{Known, Unknown};
+use crate::lang::job_table::{self, JobId};
 
 lazy_static! {
     static ref PS_OUTPUT_TYPE: Vec<ColumnType> = vec![
@@ -28,6 +29,11 @@ lazy_static! {
         ColumnType::new("cpu", ValueType::Duration),
         ColumnType::new("name", ValueType::String),
     ];
+    static ref JOBS_OUTPUT_TYPE: Vec<ColumnType> = vec![
+        ColumnType::new("id", ValueType::Integer),
+        ColumnType::new("status", ValueType::String),
+        ColumnType::new("description", ValueType::String),
+    ];
 }
 
 fn state_name(s: psutil::process::State) -> &'static str {
@@ -93,6 +99,63 @@ fn kill(context: ExecutionContext) -> CrushResult<()> {
     context.output.send(Value::Empty())
 }
 
+fn jobs(context: ExecutionContext) -> CrushResult<()> {
+    context.arguments.check_len(0)?;
+    let output = context.output.initialize(JOBS_OUTPUT_TYPE.clone())?;
+    for job in job_table::list() {
+        output.send(Row::new(vec![
+            Value::Integer(job.id as i128),
+            Value::string(&job.state.to_string()),
+            Value::string(&job.description),
+        ]))?;
+    }
+    Ok(())
+}
+
+#[signature(
+    fg,
+    can_block=true,
+    short="Move a job into the foreground",
+    output=Known(ValueType::Empty),
+    long="Resume the job if it is stopped and wait for it to finish. The job can be
+    suspended again using Ctrl-Z. If no job id is given, the most recently
+    started background job is used.")]
+struct Fg {
+    #[description("the id of the job, as shown by the jobs command.")]
+    id: Option<i128>,
+}
+
+fn fg(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Fg = Fg::parse(context.arguments, &context.printer)?;
+    let id = match cfg.id {
+        Some(id) => id as JobId,
+        None => job_table::current()?,
+    };
+    job_table::foreground(id, &context.printer)?;
+    context.output.send(Value::Empty())
+}
+
+#[signature(
+    bg,
+    can_block=false,
+    short="Resume a stopped job in the background",
+    output=Known(ValueType::Empty),
+    long="If no job id is given, the most recently started job is used.")]
+struct Bg {
+    #[description("the id of the job, as shown by the jobs command.")]
+    id: Option<i128>,
+}
+
+fn bg(context: ExecutionContext) -> CrushResult<()> {
+    let cfg: Bg = Bg::parse(context.arguments, &context.printer)?;
+    let id = match cfg.id {
+        Some(id) => id as JobId,
+        None => job_table::current()?,
+    };
+    job_table::background(id)?;
+    context.output.send(Value::Empty())
+}
+
 pub fn declare(root: &Scope) -> CrushResult<()> {
     let e = root.create_lazy_namespace(
         "proc",
@@ -124,6 +187,18 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
     * name:string the process name"#), Unknown)?;
 
             Kill::declare(env)?;
+            env.declare_command(
+                "jobs", jobs, false,
+                "jobs", "Return a table stream containing all background and stopped jobs.",
+                Some(r#"    jobs accepts no arguments. Each row contains the following columns:
+
+    * id:integer the job id, used by the fg and bg commands
+
+    * status:string either Running or Stopped
+
+    * description:string the source code of the job"#), Known(ValueType::TableStream(JOBS_OUTPUT_TYPE.clone())))?;
+            Fg::declare(env)?;
+            Bg::declare(env)?;
             Ok(())
         }))?;
     root.r#use(&e);
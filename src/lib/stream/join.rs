@@ -1,174 +1,238 @@
-use crate::lang::execution_context::{ExecutionContext, ArgumentVector};
-use crate::lang::errors::CrushResult;
+use crate::lang::execution_context::ExecutionContext;
+use crate::lang::errors::{CrushResult, argument_error, error, mandate};
 use std::collections::HashMap;
-use crate::lang::stream::CrushStream;
-use crate::lang::errors::CrushError;
+use crate::lang::stream::{Stream, ValueSender};
 use crate::lang::table::Row;
 use crate::lang::table::ColumnType;
-use crate::lang::value::ValueType;
-use crate::lang::value::Value;
+use crate::lang::value::{Field, Value, ValueType};
 use crate::lang::stream::OutputStream;
-use crate::util::replace::Replace;
-use crate::lang::errors::argument_error;
-use crate::lang::r#struct::Struct;
 use crate::lang::table::ColumnVec;
-use crate::lang::argument::Argument;
-use crate::lang::printer::Printer;
-
-pub struct Config {
-    left_table_idx: usize,
-    right_table_idx: usize,
-    left_column_idx: usize,
-    right_column_idx: usize,
+use signature::signature;
+use crate::lang::argument::{Argument, ArgumentHandler};
+use crate::lang::r#struct::Struct;
+
+#[signature(
+join,
+can_block = true,
+short = "Join two streams together on the specified keys",
+long = "The input is joined with the stream or table given as the first argument, and rows where all key columns are equal are combined into one row. The output contains the columns of the input, followed by the columns of the other side, except its key columns.",
+long = "An inner join only outputs rows that have a match. A left, right or full join also outputs the rows of the input, of the other side, or of both that have no match, with the missing cells set to empty.",
+long = "If the key columns are named differently on the two sides, use left and right instead of listing them. Columns of the other side whose names are already taken are prefixed with right_.",
+long = "The input may also be a struct with the two streams to join as members, in which case the arguments are the two key columns, prefixed with the names of the members, e.g. data l=home r=age | join ^l:name ^r:name.",
+example = "csv:from home.csv name=string country=string | join (csv:from age.csv name=string age=integer) ^name kind=left")]
+pub struct Join {
+    #[description("the stream or table to join the input with.")]
+    other: Stream,
+    #[unnamed()]
+    #[description("the columns to join on, which must exist on both sides.")]
+    key: Vec<Field>,
+    #[description("the columns of the input to join on.")]
+    left: Vec<Field>,
+    #[description("the columns of the other side to join on, in the same order as left.")]
+    right: Vec<Field>,
+    #[description("the kind of join to perform.")]
+    #[values("inner", "left", "right", "full")]
+    #[default("inner")]
+    kind: String,
 }
 
-pub fn get_sub_type(cell_type: &ValueType) -> Result<&[ColumnType], CrushError> {
-    match cell_type {
-        ValueType::TableStream(sub_types) | ValueType::Table(sub_types) => Ok(sub_types),
-        _ => argument_error("Expected a table column"),
-    }
+fn find_keys(fields: &[Field], types: &[ColumnType]) -> CrushResult<Vec<usize>> {
+    fields.iter()
+        .map(|field| types.find(field))
+        .collect()
 }
 
-pub fn guess_tables(input_type: &[ColumnType]) -> Result<(usize, usize, &[ColumnType], &[ColumnType]), CrushError> {
-    let tables: Vec<(usize, &Vec<ColumnType>)> = input_type.iter().enumerate().flat_map(|(idx, t)| {
-        match &t.cell_type {
-            ValueType::TableStream(sub_types) | ValueType::Table(sub_types) => Some((idx, sub_types)),
-            _ => None,
+fn key(row: &Row, columns: &[usize]) -> CrushResult<Vec<Value>> {
+    columns.iter()
+        .map(|idx| {
+            let value = row.cells()[*idx].clone();
+            if value.value_type().is_hashable() {
+                Ok(value)
+            } else {
+                error(format!("Can't join on a value of type {}", value.value_type().to_string()).as_str())
+            }
+        })
+        .collect()
+}
+
+fn combine(left: &Row, right: &Row, right_keys: &[usize]) -> Row {
+    let mut cells = left.cells().clone();
+    for (idx, c) in right.cells().iter().enumerate() {
+        if !right_keys.contains(&idx) {
+            cells.push(c.clone());
         }
-    }).collect();
-    if tables.len() == 2 {
-        Ok((tables[0].0, tables[1].0, tables[0].1, tables[1].1))
-    } else {
-        argument_error(format!("Could not guess tables to join, expected two tables, found {}", tables.len()).as_str())
     }
+    Row::new(cells)
 }
 
-fn scan_table(table: &str, column: &str, input_type: &[ColumnType]) -> Result<(usize, usize), CrushError> {
-    let table_idx = input_type.find_str(&table.to_string())?;
-    let column_idx = get_sub_type(&input_type[table_idx].cell_type)?.find_str(&column.to_string())?;
-    Ok((table_idx, column_idx))
+fn unmatched_left(left: &Row, right_type: &[ColumnType], right_keys: &[usize]) -> Row {
+    let mut cells = left.cells().clone();
+    for _ in 0..(right_type.len() - right_keys.len()) {
+        cells.push(Value::Empty());
+    }
+    Row::new(cells)
 }
 
-fn parse(input_type: &[ColumnType], arguments: Vec<Argument>) -> Result<Config, CrushError> {
-    arguments.check_len(2)?;
-
-    match (&arguments[0].value, &arguments[1].value) {
-        (Value::Field(l), Value::Field(r)) => {
-            let config = match (l.len(), r.len()) {
-                (1, 1) => {
-                    let (left_table_idx, right_table_idx, left_types, right_types) = guess_tables(&input_type)?;
-
-                    Config {
-                        left_table_idx,
-                        right_table_idx,
-                        left_column_idx: left_types.find(&l)?,
-                        right_column_idx: right_types.find(&r)?,
-                    }
-                }
-                (2, 2) => {
-                    let (left_table_idx, left_column_idx) =
-                        scan_table(l[0].as_ref(), l[1].as_ref(), &input_type)?;
-
-                    let (right_table_idx, right_column_idx) =
-                        scan_table(r[0].as_ref(), r[1].as_ref(), &input_type)?;
-
-                    if left_table_idx == right_table_idx {
-                        return argument_error("Left and right table can't be the same");
-                    }
-
-                    Config {
-                        left_table_idx,
-                        right_table_idx,
-                        left_column_idx,
-                        right_column_idx,
-                    }
-                }
-                _ => return argument_error("Expected both fields on the form %table.column or %column"),
-            };
+fn unmatched_right(right: &Row, left_type: &[ColumnType], left_keys: &[usize], right_keys: &[usize]) -> Row {
+    let mut cells = vec![Value::Empty(); left_type.len()];
+    for (l, r) in left_keys.iter().zip(right_keys.iter()) {
+        cells[*l] = right.cells()[*r].clone();
+    }
+    for (idx, c) in right.cells().iter().enumerate() {
+        if !right_keys.contains(&idx) {
+            cells.push(c.clone());
+        }
+    }
+    Row::new(cells)
+}
 
-            let r_type = &get_sub_type(&input_type[config.right_table_idx].cell_type)?[config.right_column_idx].cell_type;
-            let l_type = &get_sub_type(&input_type[config.left_table_idx].cell_type)?[config.left_column_idx].cell_type;
-            if r_type != l_type {
-                return argument_error("Cannot join two columns of different types");
+/**
+The key columns of the struct form of join, where they are given as
+^member:column. If the struct has exactly two members that are tables, the
+names of the members may be left out.
+*/
+fn members(input: &Struct, arguments: Vec<Argument>) -> CrushResult<(Stream, Stream, Field, Field)> {
+    let fields = arguments.into_iter()
+        .map(|a| match a.value {
+            Value::Field(f) => Ok(f),
+            _ => argument_error("Expected arguments like ^table1:column ^table2:column"),
+        })
+        .collect::<CrushResult<Vec<Field>>>()?;
+    if fields.len() != 2 {
+        return argument_error("Expected two key columns");
+    }
+    let (left, right) = (&fields[0], &fields[1]);
+    match (left.len(), right.len()) {
+        (1, 1) => {
+            let mut tables = input.local_elements().into_iter()
+                .filter(|(_, v)| matches!(v.value_type(), ValueType::Table(_) | ValueType::TableStream(_)))
+                .flat_map(|(_, v)| v.stream())
+                .collect::<Vec<Stream>>();
+            if tables.len() != 2 {
+                return argument_error(
+                    format!("Could not guess tables to join, expected two tables, found {}", tables.len()).as_str());
             }
-            if !r_type.is_hashable() {
-                argument_error("Cannot join on this column type. (It is either mutable or not comparable)")
-            } else {
-                Ok(config)
+            Ok((tables.remove(0), tables.remove(0), left.clone(), right.clone()))
+        }
+        (2, 2) => {
+            if left[0] == right[0] {
+                return argument_error("Left and right table can't be the same");
             }
+            let member = |name: &str| mandate(
+                input.get(name).and_then(|v| v.stream()),
+                format!("Expected {} to be a table", name).as_str());
+            Ok((member(&left[0])?, member(&right[0])?, left[1..].to_vec(), right[1..].to_vec()))
         }
-        _ => argument_error("Expected arguments like %table1.col == %table2.col"),
+        _ => argument_error("Expected both fields on the form ^table:column or ^column"),
     }
 }
 
-fn combine(mut l: Row, r: Row, cfg: &Config) -> Row {
-    for (idx, c) in r.into_vec().drain(..).enumerate() {
-        if idx != cfg.right_column_idx {
-            l.push(c);
+pub fn join(context: ExecutionContext) -> CrushResult<()> {
+    match context.input.recv()? {
+        Value::Struct(s) => {
+            let (left, right, left_key, right_key) = members(&s, context.arguments)?;
+            perform(left, right, &[left_key], &[right_key], "inner", context.output)
+        }
+        input => {
+            let cfg: Join = Join::parse(context.arguments, &context.printer)?;
+            let input = mandate(input.stream(), "Expected a stream")?;
+            match (cfg.key.is_empty(), cfg.left.is_empty(), cfg.right.is_empty()) {
+                (false, true, true) => perform(input, cfg.other, &cfg.key, &cfg.key, &cfg.kind, context.output),
+                (true, false, false) => {
+                    if cfg.left.len() != cfg.right.len() {
+                        return argument_error("Expected left and right to name the same number of columns");
+                    }
+                    perform(input, cfg.other, &cfg.left, &cfg.right, &cfg.kind, context.output)
+                }
+                (true, true, true) => argument_error("Missing join key"),
+                _ => argument_error("Expected either key columns, or left and right columns, but not both"),
+            }
         }
     }
-    l
 }
 
-fn do_join(cfg: &Config, l: &mut dyn CrushStream, r: &mut dyn CrushStream, output: &OutputStream, printer: &Printer) -> CrushResult<()> {
-    let mut l_data: HashMap<Value, Row> = HashMap::new();
-    while let Ok(row) = l.read() {
-        l_data.insert(row.cells()[cfg.left_column_idx].clone(), row);
-    }
-
-    while let Ok(r_row) = r.read() {
-        l_data.remove(&r_row.cells()[cfg.right_column_idx])
-            .map(|l_row| {
-                printer.handle_error(output.send(combine(l_row, r_row, cfg)));
-            });
-    }
-    Ok(())
+/**
+The type of a column of the output. Cells of a column that is missing on one
+side of an outer join may be empty, so the column can hold any type.
+*/
+fn output_column(name: &str, column: &ColumnType, nullable: bool) -> ColumnType {
+    ColumnType::new(name, if nullable { ValueType::Any } else { column.cell_type.clone() })
 }
 
-pub fn run(
-    config: Config,
-    row: Struct,
-    output: OutputStream,
-    printer: &Printer,
+fn perform(
+    mut input: Stream,
+    mut other: Stream,
+    left: &[Field],
+    right: &[Field],
+    kind: &str,
+    output: ValueSender,
 ) -> CrushResult<()> {
-    let mut v = row.to_vec();
-    match (v.replace(config.left_table_idx, Value::Integer(0)).stream(), v.replace(config.right_table_idx, Value::Integer(0)).stream()) {
-        (Some(mut l), Some(mut r)) =>
-            do_join(&config, l.as_mut(), r.as_mut(), &output, printer),
-        _ => panic!("Wrong row format"),
+    let left_type = input.types().to_vec();
+    let right_type = other.types().to_vec();
+    let left_keys = find_keys(left, &left_type)?;
+    let right_keys = find_keys(right, &right_type)?;
+
+    for (l, r) in left_keys.iter().zip(right_keys.iter()) {
+        let l_type = &left_type[*l].cell_type;
+        let r_type = &right_type[*r].cell_type;
+        if l_type != r_type && *l_type != ValueType::Any && *r_type != ValueType::Any {
+            return argument_error("Cannot join two columns of different types");
+        }
+        if !l_type.is_hashable() || !r_type.is_hashable() {
+            return argument_error("Cannot join on this column type. (It is either mutable or not comparable)");
+        }
     }
-}
 
-fn get_output_type(input_type: &[ColumnType], cfg: &Config) -> Result<Vec<ColumnType>, CrushError> {
-    let tables: Vec<Option<&Vec<ColumnType>>> = input_type.iter().map(|t| {
-        match &t.cell_type {
-            ValueType::TableStream(sub_types) | ValueType::Table(sub_types) => Some(sub_types),
-            _ => None,
+    let (keep_left, keep_right) = match kind {
+        "left" => (true, false),
+        "right" => (false, true),
+        "full" => (true, true),
+        _ => (false, false),
+    };
+
+    let mut output_type = left_type.iter()
+        .enumerate()
+        .map(|(idx, t)| output_column(&t.name, t, keep_right && !left_keys.contains(&idx)))
+        .collect::<Vec<ColumnType>>();
+    for (idx, t) in right_type.iter().enumerate() {
+        if !right_keys.contains(&idx) {
+            let mut name = t.name.clone();
+            while output_type.iter().any(|c| c.name == name) {
+                name = format!("right_{}", name);
+            }
+            output_type.push(output_column(&name, t, keep_left));
         }
-    }).collect();
-
-    match (tables[cfg.left_table_idx], tables[cfg.right_table_idx]) {
-        (Some(v1), Some(v2)) => {
-            let mut res = v1.clone();
-            for (idx, c) in v2.iter().enumerate() {
-                if idx != cfg.right_column_idx {
-                    res.push(c.clone());
+    }
+    let output: OutputStream = output.initialize(output_type)?;
+
+    let mut right_rows = Vec::new();
+    let mut index: HashMap<Vec<Value>, Vec<usize>> = HashMap::new();
+    while let Ok(row) = other.read() {
+        index.entry(key(&row, &right_keys)?).or_default().push(right_rows.len());
+        right_rows.push(row);
+    }
+    let mut matched = vec![false; right_rows.len()];
+
+    while let Ok(row) = input.read() {
+        match index.get(&key(&row, &left_keys)?) {
+            Some(matches) => {
+                for idx in matches {
+                    matched[*idx] = true;
+                    output.send(combine(&row, &right_rows[*idx], &right_keys))?;
                 }
             }
-            Ok(res)
+            None => if keep_left {
+                output.send(unmatched_left(&row, &right_type, &right_keys))?;
+            }
         }
-        _ => argument_error("Impossible error?"),
     }
-}
 
-pub fn perform(context: ExecutionContext) -> CrushResult<()> {
-    match context.input.recv()? {
-        Value::Struct(s) => {
-            let cfg = parse(&s.local_signature(), context.arguments)?;
-            let output_type = get_output_type(&s.local_signature(), &cfg)?;
-            let output = context.output.initialize(output_type)?;
-            run(cfg, s, output, &context.printer)
+    if keep_right {
+        for (row, matched) in right_rows.iter().zip(matched.iter()) {
+            if !matched {
+                output.send(unmatched_right(row, &left_type, &left_keys, &right_keys))?;
+            }
         }
-        _ => argument_error("Expected a struct"),
     }
+    Ok(())
 }
@@ -17,7 +17,7 @@ mod uniq;
 mod group;
 mod join;
 mod zip;
-//mod aggr;
+mod aggr;
 
 mod count;
 mod sum_avg;
@@ -43,17 +43,14 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
                 "group", group::perform, true,
                 "group group=field|string", "Group io by the specified column", None,
                 Unknown)?;
-            env.declare_command(
-                "join", join::perform, true,
-                "join left:field right:field", "Join two streams together on the specified keys", None,
-                Unknown)?;
+            join::Join::declare(env)?;
             env.declare_command(
                 "uniq", uniq::uniq, true,
                 "uniq column:field",
                 "Only output the first row if multiple rows has the same value for the specified column",
                 example!("ps | uniq ^user"),
                 Passthrough)?;
-            //env.declare_str("aggr", Value::Command(CrushCommand::command_undocumented(aggr::perform)))?;
+            aggr::Aggr::declare(env)?;
             env.declare_command(
                 "count", count::perform, true,
                 "count",
@@ -67,6 +67,7 @@ pub fn run(
                                 env: context.env.clone(),
                                 this: None,
                                 printer: context.printer.clone(),
+                                job_id: context.job_id,
                             }
                         )?;
                         receiver.recv()?
@@ -116,6 +117,7 @@ pub fn run(
                             env: context.env.clone(),
                             this: None,
                             printer: context.printer.clone(),
+                            job_id: context.job_id,
                         }
                     )?;
                     receiver.recv()?
@@ -3,38 +3,119 @@ use crate::{
     lang::stream::OutputStream,
 };
 use crate::lang::execution_context::ExecutionContext;
-use crate::lang::table::Row;
+use crate::lang::table::{ColumnType, Row};
 use crate::lang::errors::{CrushResult, error};
-use crate::lang::stream::CrushStream;
+use crate::lang::stream::{CrushStream, channels, empty_channel, black_hole};
 use crate::lang::table::ColumnVec;
 use signature::signature;
-use crate::lang::argument::ArgumentHandler;
-use crate::lang::value::Field;
+use crate::lang::argument::{Argument, ArgumentHandler};
+use crate::lang::value::{Field, Value};
+use crate::lang::command::Command;
 use crate::lang::command::OutputType::Passthrough;
+use std::cmp::Ordering;
 
 #[signature(
     sort,
     can_block=true,
     short="Sort io based on column",
-    long="ps | sort ^cpu",
+    long="Rows are sorted on the first column, rows where the first column is equal are sorted on the second column, and so on. The sort is stable, i.e. rows where all columns are equal keep their order.",
+    example="ps | sort ^user ^cpu reverse=false reverse=true",
     output=Passthrough)]
 pub struct Sort {
-    #[description("the column to sort on. Not required if there is only one column.")]
-    field: Option<Field>,
+    #[unnamed()]
+    #[description("the columns to sort on. Not required if there is only one column or if a key is given.")]
+    field: Vec<Field>,
+    #[description("sort in descending order. If given once, it applies to all columns, otherwise it must be given once per column.")]
+    reverse: Vec<bool>,
+    #[description("a command that calculates the value to sort each row on, before any columns. The columns of the row are passed to it as named arguments.")]
+    key: Option<Command>,
 }
 
-pub fn run(idx: usize, input: &mut dyn CrushStream, output: OutputStream) -> CrushResult<()> {
-    let mut res: Vec<Row> = Vec::new();
+fn evaluate_key(
+    key: &Command,
+    row: &Row,
+    input_type: &[ColumnType],
+    base_context: &ExecutionContext) -> CrushResult<Value> {
+    let arguments = row.clone().into_vec()
+        .drain(..)
+        .zip(input_type.iter())
+        .map(|(c, t)| Argument::named(t.name.as_ref(), c))
+        .collect();
+
+    let (sender, receiver) = channels();
+    key.invoke(base_context.clone().with_args(arguments, None).with_sender(sender))?;
+    Ok(receiver.recv()?.materialize())
+}
+
+fn compare(a: &[Value], b: &[Value], reverse: &[bool]) -> Result<Ordering, (Value, Value)> {
+    for ((a, b), reverse) in a.iter().zip(b.iter()).zip(reverse.iter()) {
+        match a.partial_cmp(b) {
+            Some(Ordering::Equal) => {}
+            Some(ordering) => return Ok(if *reverse { ordering.reverse() } else { ordering }),
+            None => return Err((a.clone(), b.clone())),
+        }
+    }
+    Ok(Ordering::Equal)
+}
+
+fn incomparable<T>(a: &Value, b: &Value) -> CrushResult<T> {
+    error(format!(
+        "Can't compare the values {} and {} of types {} and {}",
+        a.to_string(), b.to_string(), a.value_type().to_string(), b.value_type().to_string()).as_str())
+}
+
+/**
+Check that the keys can be compared before sorting them, since a sort can't
+be stopped halfway through. Keys that can be compared to the first key of the
+same column can be compared to each other, except for some nested values, e.g.
+lists with elements of different types.
+*/
+fn check_comparable(keys: &[(Vec<Value>, Row)]) -> CrushResult<()> {
+    if let Some((first, _)) = keys.first() {
+        for (other, _) in keys {
+            for (a, b) in first.iter().zip(other.iter()) {
+                if a.partial_cmp(b).is_none() {
+                    return incomparable(a, b);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn run(
+    key: Option<Command>,
+    columns: Vec<usize>,
+    reverse: Vec<bool>,
+    input: &mut dyn CrushStream,
+    output: OutputStream,
+    base_context: &ExecutionContext,
+) -> CrushResult<()> {
+    let input_type = input.types().to_vec();
+    let mut res: Vec<(Vec<Value>, Row)> = Vec::new();
     while let Ok(row) = input.read() {
-        res.push(row);
+        let mut keys = Vec::with_capacity(columns.len() + 1);
+        if let Some(key) = &key {
+            keys.push(evaluate_key(key, &row, &input_type, base_context)?);
+        }
+        for idx in &columns {
+            keys.push(row.cells()[*idx].clone());
+        }
+        res.push((keys, row));
     }
 
-    res.sort_by(|a, b|
-        a.cells()[idx]
-            .partial_cmp(&b.cells()[idx])
-            .expect("OH NO!"));
+    check_comparable(&res)?;
+    let mut failure = None;
+    res.sort_by(|(a, _), (b, _)|
+        compare(a, b, &reverse).unwrap_or_else(|values| {
+            failure.get_or_insert(values);
+            Ordering::Equal
+        }));
+    if let Some((a, b)) = failure {
+        return incomparable(&a, &b);
+    }
 
-    for row in res {
+    for (_, row) in res {
         output.send(row)?;
     }
 
@@ -46,16 +127,38 @@ pub fn sort(context: ExecutionContext) -> CrushResult<()> {
         Some(mut input) => {
             let output = context.output.initialize(input.types().to_vec())?;
             let cfg: Sort = Sort::parse(context.arguments, &context.printer)?;
-            let idx = match cfg.field {
-                None => if input.types().len() == 1 {0} else {return argument_error("Missing comparison key"); },
-                Some(field) => input.types().find(&field)?,
+            let columns = if cfg.field.is_empty() && cfg.key.is_none() {
+                if input.types().len() == 1 { vec![0] } else { return argument_error("Missing comparison key"); }
+            } else {
+                cfg.field.iter()
+                    .map(|field| input.types().find(field))
+                    .collect::<CrushResult<Vec<usize>>>()?
             };
 
-            if input.types()[idx].cell_type.is_comparable() {
-                run(idx, input.as_mut(), output)
-            } else {
-                argument_error("Bad comparison key")
+            for idx in &columns {
+                if !input.types()[*idx].cell_type.is_comparable() {
+                    return argument_error("Bad comparison key");
+                }
             }
+
+            let key_count = columns.len() + if cfg.key.is_some() { 1 } else { 0 };
+            let reverse = match cfg.reverse.len() {
+                0 => vec![false; key_count],
+                1 => vec![cfg.reverse[0]; key_count],
+                n if n == key_count => cfg.reverse,
+                _ => return argument_error("Expected reverse to be given once, or once per sort key"),
+            };
+
+            let base_context = ExecutionContext {
+                input: empty_channel(),
+                output: black_hole(),
+                arguments: vec![],
+                env: context.env.clone(),
+                this: None,
+                printer: context.printer.clone(),
+                job_id: context.job_id,
+            };
+            run(cfg.key, columns, reverse, input.as_mut(), output, &base_context)
         }
         None => error("Expected a stream"),
     }
@@ -1,5 +1,5 @@
 use crate::lang::execution_context::ExecutionContext;
-use crate::lang::errors::{CrushResult, error, argument_error};
+use crate::lang::errors::{CrushResult, error, argument_error, mandate};
 use crate::{
     lang::{
         value::ValueType,
@@ -11,6 +11,8 @@ use crate::lang::stream::Stream;
 use crate::lang::table::ColumnVec;
 use chrono::Duration;
 use float_ord::FloatOrd;
+use crate::lib::types::integer::arithmetic;
+use std::cmp::Ordering;
 
 fn parse(input_type: &[ColumnType], arguments: &[Argument]) -> CrushResult<usize> {
     match arguments.len() {
@@ -52,7 +54,19 @@ match row.cells()[column] {
     }
 }
 
-sum_function!(sum_int, i128, 0, Integer);
+/**
+Integer sums are promoted to arbitrary precision instead of overflowing.
+*/
+fn sum_int(mut s: Stream, column: usize) -> CrushResult<Value> {
+    let mut res = Value::Integer(0);
+    while let Ok(row) = s.read() {
+        res = mandate(
+            arithmetic(&res, &row.cells()[column], i128::checked_add, |a, b| a + b),
+            "Invalid cell value")?;
+    }
+    Ok(res)
+}
+
 sum_function!(sum_float, f64, 0.0, Float);
 sum_function!(sum_duration, Duration, Duration::seconds(0), Duration);
 
@@ -93,7 +107,23 @@ fn $name(mut s: Stream, column: usize) -> CrushResult<Value> {
     }
 }
 
-avg_function!(avg_int, i128, 0, Integer, i128);
+fn avg_int(mut s: Stream, column: usize) -> CrushResult<Value> {
+    let mut res = Value::Integer(0);
+    let mut count: i128 = 0;
+    while let Ok(row) = s.read() {
+        count += 1;
+        res = mandate(
+            arithmetic(&res, &row.cells()[column], i128::checked_add, |a, b| a + b),
+            "Invalid cell value")?;
+    }
+    if count == 0 {
+        return error("Can't calculate average of an empty stream");
+    }
+    mandate(
+        arithmetic(&res, &Value::Integer(count), i128::checked_div, |a, b| a / b),
+        "Invalid cell value")
+}
+
 avg_function!(avg_float, f64, 0.0, Float, f64);
 avg_function!(avg_duration, Duration, Duration::seconds(0), Duration, i32);
 
@@ -130,12 +160,39 @@ fn $name(mut s: Stream, column: usize) -> CrushResult<Value> {
     }
 }
 
-aggr_function!(min_int, Integer, |a, b| std::cmp::min(a,b));
+/**
+Integer cells can be either small or arbitrary precision, so they are
+compared as values.
+*/
+fn pick_int(mut s: Stream, column: usize, keep: Ordering) -> CrushResult<Value> {
+    let mut res = s.read()?.cells()[column].clone();
+    while let Ok(row) = s.read() {
+        let cell = &row.cells()[column];
+        match cell.partial_cmp(&res) {
+            Some(ordering) => if ordering == keep {
+                res = cell.clone();
+            },
+            None => return error("Invalid cell value, expected an integer"),
+        }
+    }
+    match res.value_type() {
+        ValueType::Integer => Ok(res),
+        _ => error("Invalid cell value, expected an integer"),
+    }
+}
+
+fn min_int(s: Stream, column: usize) -> CrushResult<Value> {
+    pick_int(s, column, Ordering::Less)
+}
+
 aggr_function!(min_float, Float, |a, b| std::cmp::min(FloatOrd(a),FloatOrd(b)).0);
 aggr_function!(min_duration, Duration, |a, b| std::cmp::min(a,b));
 aggr_function!(min_time, Time, |a, b| std::cmp::min(a,b));
 
-aggr_function!(max_int, Integer, |a, b| std::cmp::max(a,b));
+fn max_int(s: Stream, column: usize) -> CrushResult<Value> {
+    pick_int(s, column, Ordering::Greater)
+}
+
 aggr_function!(max_float, Float, |a, b| std::cmp::max(FloatOrd(a),FloatOrd(b)).0);
 aggr_function!(max_duration, Duration, |a, b| std::cmp::max(a,b));
 aggr_function!(max_time, Time, |a, b| std::cmp::max(a,b));
@@ -58,6 +58,7 @@ pub fn r#where(context: ExecutionContext) -> CrushResult<()> {
                 env: context.env.clone(),
                 this: None,
                 printer: context.printer.clone(),
+                job_id: context.job_id,
             };
             let output = context.output.initialize(input.types().to_vec())?;
             while let Ok(row) = input.read() {
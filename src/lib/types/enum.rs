@@ -0,0 +1,173 @@
+use crate::lang::errors::{CrushResult, argument_error, error};
+use crate::lang::execution_context::{ExecutionContext, CompileContext};
+use crate::lang::argument::{ArgumentDefinition, ArgumentDescription};
+use crate::lang::command::{Command, CrushCommand, OutputType};
+use crate::lang::help::Help;
+use crate::lang::serialization::SerializationState;
+use crate::lang::serialization::model::Element;
+use crate::lang::value::{Value, ValueType};
+use crate::lang::table::ColumnType;
+use crate::lang::r#struct::{Struct, Kind};
+use crate::lib::types::parse_column_types;
+use crate::lang::argument::Argument;
+
+/**
+The constructor of one variant of an enum. It is bound to the enum when
+accessed as a member of it, and the enum becomes the parent of the values it
+creates. The variant of a value is stored as the kind of its struct, not as a
+member.
+*/
+struct Variant {
+    name: String,
+    fields: Vec<ColumnType>,
+    this: Option<Value>,
+}
+
+impl Variant {
+    fn payload(&self, arguments: Vec<Argument>) -> CrushResult<Vec<(String, Value)>> {
+        let mut values: Vec<Option<Value>> = vec![None; self.fields.len()];
+        let mut unnamed = Vec::new();
+        for arg in arguments {
+            match arg.argument_type {
+                Some(name) => match self.fields.iter().position(|f| f.name == name) {
+                    Some(idx) => values[idx] = Some(arg.value),
+                    None => return argument_error(
+                        format!("Unknown member {} of variant {}", name, self.name).as_str()),
+                },
+                None => unnamed.push(arg.value),
+            }
+        }
+        let mut unnamed = unnamed.into_iter();
+        for value in values.iter_mut().filter(|v| v.is_none()) {
+            *value = unnamed.next();
+        }
+        if unnamed.next().is_some() {
+            return argument_error(format!("Too many arguments to variant {}", self.name).as_str());
+        }
+
+        self.fields.iter()
+            .zip(values)
+            .map(|(field, value)| match value {
+                Some(value) if field.cell_type.is(&value) => Ok((field.name.clone(), value)),
+                Some(value) => argument_error(
+                    format!(
+                        "Expected member {} to be of type {}, was of type {}",
+                        field.name, field.cell_type.to_string(), value.value_type().to_string()).as_str()),
+                None => argument_error(format!("Missing value for member {}", field.name).as_str()),
+            })
+            .collect()
+    }
+}
+
+impl CrushCommand for Variant {
+    fn invoke(&self, context: ExecutionContext) -> CrushResult<()> {
+        let parent = match self.this.clone().or(context.this) {
+            Some(Value::Struct(s)) if s.kind() == Some(Kind::Enum) => s,
+            _ => return error("Enum variants must be constructed through their enum"),
+        };
+        let value = Struct::new(self.payload(context.arguments)?, Some(parent));
+        value.set_kind(Some(Kind::Variant(self.name.clone())));
+        context.output.send(Value::Struct(value))
+    }
+
+    fn can_block(&self, _arguments: &[ArgumentDefinition], _context: &mut CompileContext) -> bool {
+        false
+    }
+
+    fn name(&self) -> &str { "variant" }
+
+    fn clone(&self) -> Command {
+        Box::from(Variant {
+            name: self.name.clone(),
+            fields: self.fields.clone(),
+            this: self.this.clone(),
+        })
+    }
+
+    fn help(&self) -> &dyn Help {
+        self
+    }
+
+    fn serialize(&self, _elements: &mut Vec<Element>, _state: &mut SerializationState) -> CrushResult<usize> {
+        error("Enum variants can not be serialized")
+    }
+
+    fn bind(&self, this: Value) -> Command {
+        Box::from(Variant {
+            name: self.name.clone(),
+            fields: self.fields.clone(),
+            this: Some(this),
+        })
+    }
+
+    fn output<'a>(&'a self, _input: &'a OutputType) -> Option<&'a ValueType> {
+        Some(&ValueType::Struct)
+    }
+
+    fn arguments(&self) -> Vec<ArgumentDescription> {
+        self.fields.iter()
+            .map(|f| ArgumentDescription {
+                name: f.name.clone(),
+                allowed_values: None,
+            })
+            .collect()
+    }
+}
+
+impl Help for Variant {
+    fn signature(&self) -> String {
+        format!(
+            "{} {}",
+            self.name,
+            self.fields.iter()
+                .map(|f| format!("{}:{}", f.name, f.cell_type.to_string()))
+                .collect::<Vec<_>>()
+                .join(" "))
+    }
+
+    fn short_help(&self) -> String {
+        format!("Create a new value of the {} variant", self.name)
+    }
+
+    fn long_help(&self) -> Option<String> {
+        None
+    }
+}
+
+pub fn r#enum(context: ExecutionContext) -> CrushResult<()> {
+    let mut variants = Vec::new();
+    for arg in context.arguments {
+        let (name, fields) = match (arg.argument_type, arg.value) {
+            (Some(name), Value::Struct(s)) => (
+                name,
+                parse_column_types(
+                    s.local_elements()
+                        .into_iter()
+                        .map(|(name, value)| Argument::named(&name, value))
+                        .collect())?),
+            (None, Value::Field(f)) if f.len() == 1 => (f[0].clone(), vec![]),
+            (None, Value::String(s)) => (s, vec![]),
+            _ => return argument_error(
+                "Expected variants to be either fields or named structs of member types"),
+        };
+        variants.push((
+            name.clone(),
+            Value::Command(Box::from(Variant { name, fields, this: None }))));
+    }
+    let res = Struct::new(variants, Some(context.env.root_object()));
+    res.set_kind(Some(Kind::Enum));
+    context.output.send(Value::Struct(res))
+}
+
+/**
+The variant and the members of the payload of an enum value.
+*/
+pub fn variant(value: &Value) -> Option<(String, Vec<(String, Value)>)> {
+    match value {
+        Value::Struct(s) => match s.kind() {
+            Some(Kind::Variant(name)) => Some((name, s.local_elements())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -1,12 +1,14 @@
-use crate::lang::errors::{CrushResult, argument_error};
+use crate::lang::errors::{CrushResult, argument_error, mandate};
 use crate::lang::{value::Value, execution_context::ExecutionContext};
-use crate::lang::execution_context::{ArgumentVector, This};
+use crate::lang::execution_context::ArgumentVector;
 use ordered_map::OrderedMap;
 use lazy_static::lazy_static;
 use crate::lang::command::Command;
 use crate::lang::command::TypeMap;
 use crate::lang::command::OutputType::Known;
 use crate::lang::value::ValueType;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
 
 fn full(name: &'static str) -> Vec<&'static str> {
     vec!["global", "types", "integer", name]
@@ -56,14 +58,100 @@ lazy_static! {
     };
 }
 
-binary_op!(add, integer, Integer, Integer, |a, b| a+b, Float, Float, |a, b| a as f64+b);
-binary_op!(sub, integer, Integer, Integer, |a, b| a-b, Float, Float, |a, b| a as f64-b);
-binary_op!(mul, integer, Integer, Integer, |a, b| a*b, Float, Float, |a, b| a as f64*b);
-binary_op!(div, integer, Integer, Integer, |a, b| a/b, Float, Float, |a, b| a as f64/b);
-binary_op!(rem, integer, Integer, Integer, |a, b| a % b);
-binary_op!(r#mod, integer, Integer, Integer, |a, b| (a % b + b) % b);
+fn float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::BigInteger(i) => i.to_f64(),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+fn big(value: &Value) -> Option<BigInt> {
+    match value {
+        Value::Integer(i) => Some(BigInt::from(*i)),
+        Value::BigInteger(i) => Some(i.clone()),
+        _ => None,
+    }
+}
+
+/**
+Apply an arithmetic operation to two integers. The operation is done on i128
+values, and redone with arbitrary precision if that overflows.
+*/
+pub fn arithmetic(
+    this: &Value,
+    that: &Value,
+    small: fn(i128, i128) -> Option<i128>,
+    large: fn(BigInt, BigInt) -> BigInt,
+) -> Option<Value> {
+    if let (Value::Integer(a), Value::Integer(b)) = (this, that) {
+        if let Some(res) = small(*a, *b) {
+            return Some(Value::Integer(res));
+        }
+    }
+    Some(Value::big_integer(large(big(this)?, big(that)?)))
+}
+
+fn check_divisor(value: &Value) -> CrushResult<()> {
+    match value {
+        Value::Integer(0) => argument_error("Division by zero"),
+        _ => Ok(()),
+    }
+}
+
+macro_rules! integer_op {
+    ($name:ident, $small:expr, $large:expr, $float:expr) => {
+fn $name(mut context: ExecutionContext) -> CrushResult<()> {
+    context.arguments.check_len(1)?;
+    let this = mandate(context.this, "Expected this to be an integer")?;
+    let that = context.arguments.value(0)?;
+    let res = match &that {
+        Value::Float(b) => Value::Float($float(mandate(float(&this), "Expected this to be an integer")?, *b)),
+        _ => mandate(arithmetic(&this, &that, $small, $large), "Expected only arguments of the same type")?,
+    };
+    context.output.send(res)
+}
+    };
+    ($name:ident, $small:expr, $large:expr) => {
+fn $name(mut context: ExecutionContext) -> CrushResult<()> {
+    context.arguments.check_len(1)?;
+    let this = mandate(context.this, "Expected this to be an integer")?;
+    let that = context.arguments.value(0)?;
+    check_divisor(&that)?;
+    context.output.send(mandate(arithmetic(&this, &that, $small, $large), "Expected only arguments of the same type")?)
+}
+    };
+}
+
+integer_op!(add, i128::checked_add, |a, b| a + b, |a, b| a + b);
+integer_op!(sub, i128::checked_sub, |a, b| a - b, |a, b| a - b);
+integer_op!(mul, i128::checked_mul, |a, b| a * b, |a, b| a * b);
+integer_op!(rem, i128::checked_rem, |a, b| a % b);
+integer_op!(r#mod, |a, b| a.checked_rem(b)?.checked_add(b)?.checked_rem(b), |a, b| (a % &b + &b) % b);
+
+fn div(mut context: ExecutionContext) -> CrushResult<()> {
+    context.arguments.check_len(1)?;
+    let this = mandate(context.this, "Expected this to be an integer")?;
+    let that = context.arguments.value(0)?;
+    let res = match &that {
+        Value::Float(b) => Value::Float(mandate(float(&this), "Expected this to be an integer")? / *b),
+        _ => {
+            check_divisor(&that)?;
+            mandate(arithmetic(&this, &that, i128::checked_div, |a, b| a / b), "Expected only arguments of the same type")?
+        }
+    };
+    context.output.send(res)
+}
 
 fn neg(context: ExecutionContext) -> CrushResult<()> {
     context.arguments.check_len(0)?;
-    context.output.send(Value::Integer(-context.this.integer()?))
+    match mandate(context.this, "Expected this to be an integer")? {
+        Value::Integer(i) => context.output.send(
+            i.checked_neg()
+                .map(Value::Integer)
+                .unwrap_or_else(|| Value::big_integer(-BigInt::from(i)))),
+        Value::BigInteger(i) => context.output.send(Value::big_integer(-i)),
+        v => argument_error(format!("Expected this to be an integer, but it is a {}", v.value_type().to_string()).as_str()),
+    }
 }
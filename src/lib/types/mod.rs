@@ -24,6 +24,7 @@ pub mod duration;
 pub mod time;
 pub mod binary;
 pub mod scope;
+pub mod r#enum;
 
 fn materialize(context: ExecutionContext) -> CrushResult<()> {
     context.output.send(context.input.recv()?.materialize())
@@ -115,25 +116,25 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
                         vec!["global".to_string(), "types".to_string(), "root".to_string(), "__setattr__".to_string()],
                         "root:__setitem__ name:string value:any",
                         "Modify the specified field to hold the specified value",
-                        None, Known(ValueType::Empty)))),
+                        None, Known(ValueType::Empty), vec![]))),
                     ("__getitem__".to_string(), Value::Command(CrushCommand::command(
                         class_get, false,
                         vec!["global".to_string(), "types".to_string(), "root".to_string(), "__getitem__".to_string()],
                         "root:__getitem__ name:string",
                         "Return the value of the specified field",
-                        None, Unknown))),
+                        None, Unknown, vec![]))),
                     ("__setitem__".to_string(), Value::Command(CrushCommand::command(
                         class_get, false,
                         vec!["global".to_string(), "types".to_string(), "root".to_string(), "__setitem__".to_string()],
                         "root:__setitem__ name:string value:any",
                         "Modify the specified field to hold the specified value",
-                        None, Unknown))),
+                        None, Unknown, vec![]))),
                     ("new".to_string(), Value::Command(CrushCommand::command(
                         new, true,
                         vec!["global".to_string(), "types".to_string(), "root".to_string(), "new".to_string()],
                         "root:new @unnamed @@named",
                         "Create a new instance of the specified type",
-                        None, Known(ValueType::Struct)))),
+                        None, Known(ValueType::Struct), vec![]))),
                 ], None);
 
             env.declare("root", Value::Struct(root))?;
@@ -184,6 +185,25 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
 
     p := (Point:new x=1.0 y=2.0)
     p:len"#), Known(ValueType::Type))?;
+            env.declare_command(
+                "enum", r#enum::r#enum, false,
+                "enum [variant:field...] [<name>=members:struct...]",
+                "Create a new enum type with the specified variants",
+                Some(r#"    Variants without a payload are specified as fields. The members of the
+    payload of a variant and their types are specified as a struct. Every
+    variant becomes a member of the enum that creates values of that variant.
+    Values can be taken apart using the match command.
+
+    Example:
+
+    Shape := (enum ^point circle=(data radius=float) rect=(data width=float height=float))
+
+    Shape:area = {
+        ||
+        match this ^point {0.0} ^circle {|radius| radius*radius*3.14} ^rect {|width height| width*height}
+    }
+
+    (Shape:circle radius=2.0):area"#), Known(ValueType::Struct))?;
             env.declare_command(
                 "materialize", materialize, true,
                 "materialize",
@@ -8,11 +8,12 @@ mod util;
 use rustyline;
 
 use rustyline::error::ReadlineError;
-use rustyline::Editor;
+use rustyline::{Editor, Config, CompletionType};
 use lib::declare;
 use crate::lang::errors::{CrushResult, to_crush_error};
-use crate::lang::{printer, execute};
+use crate::lang::{printer, execute, job_table, parser};
 use crate::lang::pretty_printer::create_pretty_printer;
+use crate::lang::line_editor::CrushHelper;
 use crate::util::file::home;
 use std::path::{PathBuf, Path};
 use crate::lang::scope::Scope;
@@ -32,20 +33,34 @@ fn crush_history_file() -> String {
 fn run_interactive(global_env: Scope, printer: &Printer, pretty_printer: &ValueSender) -> CrushResult<()> {
     printer.line("Welcome to Crush");
     printer.line(r#"Type "help" for... help."#);
+    job_table::init()?;
 
-    let mut rl = Editor::<()>::new();
+    let config = Config::builder()
+        .completion_type(CompletionType::List)
+        .build();
+    let mut rl = Editor::with_config(config);
+    rl.set_helper(Some(CrushHelper::new(&global_env)));
     let _ = rl.load_history(&crush_history_file());
+    let mut cmd = String::new();
     loop {
-        let readline = rl.readline("crush> ");
+        let prompt = if cmd.is_empty() { "crush> " } else { "     > " };
+        let readline = rl.readline(prompt);
 
         match readline {
-            Ok(cmd) => {
+            Ok(line) => {
+                cmd.push_str(&line);
+                if !parser::is_complete(&cmd) {
+                    cmd.push('\n');
+                    continue;
+                }
                 if !cmd.is_empty() {
                     rl.add_history_entry(cmd.as_str());
                     execute::string(global_env.clone(), &cmd.as_str(), &printer, pretty_printer);
                 }
+                cmd.clear();
             }
             Err(ReadlineError::Interrupted) => {
+                cmd.clear();
                 printer.line("^C");
             }
             Err(ReadlineError::Eof) => {
@@ -77,17 +92,17 @@ fn run() -> CrushResult<()> {
     let args = std::env::args().collect::<Vec<String>>();
     match args.len() {
         1 => run_interactive(
-            my_scope,
+            my_scope.clone(),
             &printer,
             &pretty_printer)?,
         2 =>
             if args[1] == "--pup" {
                 let mut buff = Vec::new();
                 to_crush_error(std::io::stdin().read_to_end(&mut buff))?;
-                execute::pup(my_scope, &buff, &printer)?;
+                execute::pup(my_scope.clone(), &buff, &printer)?;
             } else {
                 execute::file(
-                    my_scope,
+                    my_scope.clone(),
                     PathBuf::from(&args[1]).as_path(),
                     &printer,
                     &pretty_printer)?
@@ -96,6 +111,7 @@ fn run() -> CrushResult<()> {
     }
     drop(pretty_printer);
     drop(printer);
+    my_scope.clear();
     global_env.clear();
     drop(global_env);
     let _ = print_handle.join();
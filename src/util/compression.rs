@@ -0,0 +1,95 @@
+use crate::lang::errors::{CrushResult, to_crush_error, argument_error};
+use std::io::{Read, Write, BufReader};
+use std::path::Path;
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /**
+    Guess the compression format of a file from its extension.
+    */
+    pub fn from_path(path: &Path) -> Option<Compression> {
+        match path.extension()?.to_str()? {
+            "gz" | "tgz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            "xz" => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    pub fn decoder(self, reader: impl Read + Send + 'static) -> CrushResult<Box<dyn Read + Send>> {
+        Ok(match self {
+            Compression::Gzip => Box::from(flate2::read::MultiGzDecoder::new(reader)),
+            Compression::Zstd => Box::from(to_crush_error(zstd::Decoder::with_buffer(BufReader::new(reader)))?),
+            Compression::Bzip2 => Box::from(bzip2::read::MultiBzDecoder::new(reader)),
+            Compression::Xz => Box::from(xz2::read::XzDecoder::new_multi_decoder(reader)),
+        })
+    }
+
+    /**
+    Check that the compression level is valid for this format.
+    */
+    pub fn level(self, level: Option<i128>) -> CrushResult<Option<u32>> {
+        match level {
+            None => Ok(None),
+            Some(level) => match u32::try_from(level) {
+                Ok(level) if self.levels().contains(&level) => Ok(Some(level)),
+                _ => argument_error(
+                    format!(
+                        "Compression level must be between {} and {}",
+                        self.levels().start(), self.levels().end()).as_str()),
+            },
+        }
+    }
+
+    /**
+    Compress everything in the reader into the writer. If no level is
+    given, the default level of the format is used.
+    */
+    pub fn encode(self, reader: &mut dyn Read, writer: impl Write, level: Option<u32>) -> CrushResult<()> {
+        match self {
+            Compression::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(
+                    writer,
+                    level.map(flate2::Compression::new).unwrap_or_default());
+                to_crush_error(std::io::copy(reader, &mut encoder))?;
+                to_crush_error(encoder.finish())?;
+            }
+            Compression::Zstd => {
+                let mut encoder = to_crush_error(zstd::Encoder::new(writer, level.unwrap_or(3) as i32))?;
+                to_crush_error(std::io::copy(reader, &mut encoder))?;
+                to_crush_error(encoder.finish())?;
+            }
+            Compression::Bzip2 => {
+                let mut encoder = bzip2::write::BzEncoder::new(
+                    writer,
+                    level.map(bzip2::Compression::new).unwrap_or_default());
+                to_crush_error(std::io::copy(reader, &mut encoder))?;
+                to_crush_error(encoder.finish())?;
+            }
+            Compression::Xz => {
+                let mut encoder = xz2::write::XzEncoder::new(writer, level.unwrap_or(6));
+                to_crush_error(std::io::copy(reader, &mut encoder))?;
+                to_crush_error(encoder.finish())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn levels(self) -> RangeInclusive<u32> {
+        match self {
+            Compression::Gzip | Compression::Xz => 0..=9,
+            Compression::Zstd => 1..=22,
+            Compression::Bzip2 => 1..=9,
+        }
+    }
+}
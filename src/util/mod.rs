@@ -6,3 +6,4 @@ pub mod glob;
 pub mod replace;
 pub mod regex;
 pub mod identity_arc;
+pub mod compression;
@@ -1,11 +1,12 @@
 use std::thread::JoinHandle;
 use std::thread;
 use crate::lang::job::JobJoinHandle;
+use crate::lang::errors::CrushResult;
 
 pub fn build(name: &str) -> thread::Builder {
     thread::Builder::new().name(name.to_string())
 }
 
-pub fn handle(h: Result<JoinHandle<()>, std::io::Error>) -> JobJoinHandle {
+pub fn handle(h: Result<JoinHandle<CrushResult<()>>, std::io::Error>) -> JobJoinHandle {
     JobJoinHandle::Async(h.unwrap())
 }